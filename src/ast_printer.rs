@@ -0,0 +1,507 @@
+//////////////////////////////////////
+// private module rlox::ast_printer //
+//////////////////////////////////////
+
+
+/////////
+// use //
+/////////
+
+use crate::expr::{self, Expr};
+use crate::formatter::expr_line;
+use crate::stmt::{self, Stmt};
+use crate::token::{Token, TokenType};
+
+
+//////////////////
+// declarations //
+//////////////////
+
+/// Prints the tree as S-expressions, `(+ 1 (* 2 3))`.
+pub struct AstPrinter;
+
+/// Prints the tree as JSON, every node an object naming its `type`, with
+/// variables carrying the `jump` the resolver gave them [ -1 if unresolved ].
+pub struct JsonPrinter;
+
+// just enough of JSON to print a tree with
+enum Json {
+  Null,
+  Bool( bool ),
+  Number( String ),
+  String( String ),
+  Array( Vec<Json> ),
+  Object( Vec<( &'static str, Json )> )
+}
+
+
+/////////////////////
+// implementations //
+/////////////////////
+
+impl AstPrinter {
+  pub fn new() -> AstPrinter {
+    AstPrinter
+  }
+  pub fn print_expr( &mut self, expr: &Expr ) -> String {
+    expr.accept( self )
+  }
+
+  /// One line per top-level statement.
+  pub fn print_stmts( &mut self, stmts: &[Stmt] ) -> String {
+    stmts.iter().map( | stmt | stmt.accept( self ) + "\n" ).collect()
+  }
+  fn parenthesize( &mut self, name: &str, exprs: &[&Expr] ) -> String {
+    let mut result = format!( "({}", name );
+    for expr in exprs {
+      result += " ";
+      result += &self.print_expr( expr );
+    }
+    result + ")"
+  }
+
+  // like parenthesize, for what's already printed
+  fn list( name: &str, items: Vec<String> ) -> String {
+    let mut result = format!( "({}", name );
+    for item in items {
+      result += " ";
+      result += &item;
+    }
+    result + ")"
+  }
+  fn body( &mut self, stmts: &[Stmt] ) -> Vec<String> {
+    stmts.iter().map( | stmt | stmt.accept( self ) ).collect()
+  }
+  fn params( params: &[Token] ) -> String {
+    let params: Vec<&str> = params.iter().map( | p | p.lexeme.as_str() ).collect();
+    format!( "({})", params.join( " " ) )
+  }
+}
+
+impl stmt::Visitor<String> for AstPrinter {
+  fn visit_block_stmt( &mut self, block: &stmt::Block ) -> String {
+    let body = self.body( &block.statements );
+    AstPrinter::list( "block", body )
+  }
+  fn visit_break_stmt( &mut self, _break: &stmt::Break ) -> String {
+    "(break)".into()
+  }
+  fn visit_continue_stmt( &mut self, _continue: &stmt::Continue ) -> String {
+    "(continue)".into()
+  }
+  fn visit_expression_stmt( &mut self, expression: &stmt::Expression ) -> String {
+    self.parenthesize( ";", &[ &expression.expression ] )
+  }
+  fn visit_for_in_stmt( &mut self, for_in: &stmt::ForIn ) -> String {
+    let items = vec![ for_in.name.lexeme.clone(), self.print_expr( &for_in.iterable ), for_in.body.accept( self ) ];
+    AstPrinter::list( "for-in", items )
+  }
+  fn visit_function_stmt( &mut self, function: &stmt::Function ) -> String {
+    let mut items = vec![ function.name.lexeme.clone(), AstPrinter::params( &function.params ) ];
+    items.extend( self.body( &function.body ) );
+    AstPrinter::list( "fun", items )
+  }
+  fn visit_if_stmt( &mut self, if_: &stmt::If ) -> String {
+    let mut items = vec![ self.print_expr( &if_.condition ), if_.then_branch.accept( self ) ];
+    if let Some( else_branch ) = &if_.else_branch {
+      items.push( else_branch.accept( self ) );
+    }
+    AstPrinter::list( "if", items )
+  }
+  fn visit_print_stmt( &mut self, print: &stmt::Print ) -> String {
+    self.parenthesize( "print", &[ &print.expression ] )
+  }
+  fn visit_return_stmt( &mut self, return_: &stmt::Return ) -> String {
+    match &return_.value {
+      Some( value ) => self.parenthesize( "return", &[ value ] ),
+      None => "(return)".into()
+    }
+  }
+  fn visit_throw_stmt( &mut self, throw: &stmt::Throw ) -> String {
+    self.parenthesize( "throw", &[ &throw.value ] )
+  }
+  fn visit_try_stmt( &mut self, try_: &stmt::Try ) -> String {
+    let mut items = vec![ self.visit_block_stmt( &try_.body ) ];
+    if let Some( catch ) = &try_.catch {
+      items.push( AstPrinter::list( "catch", vec![ catch.name.lexeme.clone(), self.visit_block_stmt( &catch.body ) ] ) );
+    }
+    if let Some( finally ) = &try_.finally {
+      items.push( AstPrinter::list( "finally", vec![ self.visit_block_stmt( finally ) ] ) );
+    }
+    AstPrinter::list( "try", items )
+  }
+  fn visit_var_stmt( &mut self, var: &stmt::Var ) -> String {
+    match &var.init {
+      Some( init ) => self.parenthesize( &format!( "var {}", var.name.lexeme ), &[ init ] ),
+      None => format!( "(var {})", var.name.lexeme )
+    }
+  }
+  fn visit_while_stmt( &mut self, while_: &stmt::While ) -> String {
+    let mut items = vec![ self.print_expr( &while_.condition ), while_.body.accept( self ) ];
+
+    // a desugared for-loop's increment, run after the body
+    if let Some( increment ) = &while_.increment {
+      items.push( self.parenthesize( "increment", &[ increment ] ) );
+    }
+    AstPrinter::list( "while", items )
+  }
+}
+
+impl expr::Visitor<String> for AstPrinter {
+  fn visit_assign_expr( &mut self, assign: &expr::Assign ) -> String {
+    self.parenthesize( &format!( "= {}", assign.lhs.name.lexeme ), &[ &assign.rhs ] )
+  }
+  fn visit_binary_expr( &mut self, binary: &expr::Binary ) -> String {
+    self.parenthesize( &binary.operator.lexeme, &[ &binary.left, &binary.right ] )
+  }
+  fn visit_call_expr( &mut self, call: &expr::Call ) -> String {
+    let mut exprs: Vec<&Expr> = vec![ &call.callee ];
+    for arg in &call.arguments {
+      exprs.push( arg );
+    }
+    self.parenthesize( "call", &exprs )
+  }
+  fn visit_get_expr( &mut self, get: &expr::Get ) -> String {
+    self.parenthesize( &format!( ". {}", get.name.lexeme ), &[ &get.object ] )
+  }
+  fn visit_grouping_expr( &mut self, grouping: &expr::Grouping ) -> String {
+    self.parenthesize( "group", &[ &grouping.expression ] )
+  }
+  fn visit_index_expr( &mut self, index: &expr::Index ) -> String {
+    self.parenthesize( "[]", &[ &index.object, &index.index ] )
+  }
+  fn visit_lambda_expr( &mut self, lambda: &expr::Lambda ) -> String {
+    let mut items = vec![ AstPrinter::params( &lambda.function.params ) ];
+    items.extend( self.body( &lambda.function.body ) );
+    AstPrinter::list( "lambda", items )
+  }
+  fn visit_list_expr( &mut self, list: &expr::List ) -> String {
+    let elements: Vec<&Expr> = list.elements.iter().map( | e | e.as_ref() ).collect();
+    self.parenthesize( "list", &elements )
+  }
+  fn visit_literal_expr( &mut self, literal: &expr::Literal ) -> String {
+    match literal.value.token_type {
+
+      // escaped as in JSON, so a ')' or line break in the string can't be mistaken for the tree's own
+      TokenType::String => {
+        let mut quoted = String::new();
+        write_string( &mut quoted, &literal.value.lexeme );
+        quoted
+      },
+      _ => literal.value.lexeme.clone()
+    }
+  }
+  fn visit_map_expr( &mut self, map: &expr::Map ) -> String {
+    let mut entries: Vec<&Expr> = Vec::new();
+    for ( key, value ) in &map.entries {
+      entries.push( key );
+      entries.push( value );
+    }
+    self.parenthesize( "map", &entries )
+  }
+  fn visit_set_index_expr( &mut self, set_index: &expr::SetIndex ) -> String {
+    self.parenthesize( "[]=", &[ &set_index.object, &set_index.index, &set_index.value ] )
+  }
+  fn visit_unary_expr( &mut self, unary: &expr::Unary ) -> String {
+    self.parenthesize( &unary.operator.lexeme, &[ &unary.right ] )
+  }
+  fn visit_variable_expr( &mut self, variable: &expr::Variable ) -> String {
+    variable.name.lexeme.clone()
+  }
+}
+
+impl JsonPrinter {
+  pub fn new() -> JsonPrinter {
+    JsonPrinter
+  }
+
+  /// The statements as a JSON array, indented two spaces a level.
+  pub fn print_stmts( &mut self, stmts: &[Stmt] ) -> String {
+    let mut out = String::new();
+    self.stmts( stmts ).write( &mut out, 0 );
+    out + "\n"
+  }
+  fn stmts( &mut self, stmts: &[Stmt] ) -> Json {
+    Json::Array( stmts.iter().map( | stmt | stmt.accept( self ) ).collect() )
+  }
+  fn expr( &mut self, expr: &Expr ) -> Json {
+    expr.accept( self )
+  }
+  fn exprs( &mut self, exprs: &[Box<Expr>] ) -> Json {
+    Json::Array( exprs.iter().map( | expr | self.expr( expr ) ).collect() )
+  }
+  fn optional( &mut self, expr: &Option<Expr> ) -> Json {
+    match expr {
+      Some( expr ) => self.expr( expr ),
+      None => Json::Null
+    }
+  }
+  fn block( &mut self, block: &stmt::Block ) -> Json {
+    node( "Block", vec![
+      ( "line", line( block.line ) ),
+      ( "statements", self.stmts( &block.statements ) )
+    ] )
+  }
+  fn function( &mut self, function: &stmt::Function ) -> Json {
+    node( "Function", vec![
+      ( "name", name( &function.name ) ),
+      ( "line", line( function.name.line ) ),
+      ( "params", Json::Array( function.params.iter().map( name ).collect() ) ),
+      ( "body", self.stmts( &function.body ) )
+    ] )
+  }
+}
+
+impl stmt::Visitor<Json> for JsonPrinter {
+  fn visit_block_stmt( &mut self, block: &stmt::Block ) -> Json {
+    self.block( block )
+  }
+  fn visit_break_stmt( &mut self, break_: &stmt::Break ) -> Json {
+    node( "Break", vec![ ( "line", line( break_.keyword.line ) ) ] )
+  }
+  fn visit_continue_stmt( &mut self, continue_: &stmt::Continue ) -> Json {
+    node( "Continue", vec![ ( "line", line( continue_.keyword.line ) ) ] )
+  }
+  fn visit_expression_stmt( &mut self, expression: &stmt::Expression ) -> Json {
+    node( "Expression", vec![
+      ( "line", line( expr_line( &expression.expression ) ) ),
+      ( "expression", self.expr( &expression.expression ) )
+    ] )
+  }
+  fn visit_for_in_stmt( &mut self, for_in: &stmt::ForIn ) -> Json {
+    node( "ForIn", vec![
+      ( "line", line( for_in.keyword.line ) ),
+      ( "name", name( &for_in.name ) ),
+      ( "iterable", self.expr( &for_in.iterable ) ),
+      ( "body", for_in.body.accept( self ) )
+    ] )
+  }
+  fn visit_function_stmt( &mut self, function: &stmt::Function ) -> Json {
+    self.function( function )
+  }
+  fn visit_if_stmt( &mut self, if_: &stmt::If ) -> Json {
+    let else_branch = match &if_.else_branch {
+      Some( else_branch ) => else_branch.accept( self ),
+      None => Json::Null
+    };
+    node( "If", vec![
+      ( "line", line( expr_line( &if_.condition ) ) ),
+      ( "condition", self.expr( &if_.condition ) ),
+      ( "then", if_.then_branch.accept( self ) ),
+      ( "else", else_branch )
+    ] )
+  }
+  fn visit_print_stmt( &mut self, print: &stmt::Print ) -> Json {
+    node( "Print", vec![
+      ( "line", line( expr_line( &print.expression ) ) ),
+      ( "expression", self.expr( &print.expression ) )
+    ] )
+  }
+  fn visit_return_stmt( &mut self, return_: &stmt::Return ) -> Json {
+    node( "Return", vec![
+      ( "line", line( return_.keyword.line ) ),
+      ( "value", self.optional( &return_.value ) )
+    ] )
+  }
+  fn visit_throw_stmt( &mut self, throw: &stmt::Throw ) -> Json {
+    node( "Throw", vec![
+      ( "line", line( throw.keyword.line ) ),
+      ( "value", self.expr( &throw.value ) )
+    ] )
+  }
+  fn visit_try_stmt( &mut self, try_: &stmt::Try ) -> Json {
+    let catch = match &try_.catch {
+      Some( catch ) => Json::Object( vec![
+        ( "name", name( &catch.name ) ),
+        ( "body", self.block( &catch.body ) )
+      ] ),
+      None => Json::Null
+    };
+    let finally = match &try_.finally {
+      Some( finally ) => self.block( finally ),
+      None => Json::Null
+    };
+    node( "Try", vec![
+      ( "line", line( try_.keyword.line ) ),
+      ( "body", self.block( &try_.body ) ),
+      ( "catch", catch ),
+      ( "finally", finally )
+    ] )
+  }
+  fn visit_var_stmt( &mut self, var: &stmt::Var ) -> Json {
+    node( "Var", vec![
+      ( "name", name( &var.name ) ),
+      ( "line", line( var.name.line ) ),
+      ( "init", self.optional( &var.init ) )
+    ] )
+  }
+  fn visit_while_stmt( &mut self, while_: &stmt::While ) -> Json {
+    node( "While", vec![
+      ( "line", line( while_.keyword.line ) ),
+      ( "condition", self.expr( &while_.condition ) ),
+      ( "body", while_.body.accept( self ) ),
+      ( "increment", self.optional( &while_.increment ) )
+    ] )
+  }
+}
+
+impl expr::Visitor<Json> for JsonPrinter {
+  fn visit_assign_expr( &mut self, assign: &expr::Assign ) -> Json {
+    node( "Assign", vec![
+      ( "target", self.visit_variable_expr( &assign.lhs ) ),
+      ( "value", self.expr( &assign.rhs ) )
+    ] )
+  }
+  fn visit_binary_expr( &mut self, binary: &expr::Binary ) -> Json {
+    node( "Binary", vec![
+      ( "operator", name( &binary.operator ) ),
+      ( "line", line( binary.operator.line ) ),
+      ( "left", self.expr( &binary.left ) ),
+      ( "right", self.expr( &binary.right ) )
+    ] )
+  }
+  fn visit_call_expr( &mut self, call: &expr::Call ) -> Json {
+    node( "Call", vec![
+      ( "line", line( call.paren.line ) ),
+      ( "callee", self.expr( &call.callee ) ),
+      ( "arguments", self.exprs( &call.arguments ) )
+    ] )
+  }
+  fn visit_get_expr( &mut self, get: &expr::Get ) -> Json {
+    node( "Get", vec![
+      ( "object", self.expr( &get.object ) ),
+      ( "name", name( &get.name ) )
+    ] )
+  }
+  fn visit_grouping_expr( &mut self, grouping: &expr::Grouping ) -> Json {
+    node( "Grouping", vec![ ( "expression", self.expr( &grouping.expression ) ) ] )
+  }
+  fn visit_index_expr( &mut self, index: &expr::Index ) -> Json {
+    node( "Index", vec![
+      ( "line", line( index.bracket.line ) ),
+      ( "object", self.expr( &index.object ) ),
+      ( "index", self.expr( &index.index ) )
+    ] )
+  }
+  fn visit_lambda_expr( &mut self, lambda: &expr::Lambda ) -> Json {
+    node( "Lambda", vec![
+      ( "line", line( lambda.keyword.line ) ),
+      ( "arrow", Json::Bool( lambda.keyword.token_type == TokenType::Arrow ) ),
+      ( "function", self.function( &lambda.function ) )
+    ] )
+  }
+  fn visit_list_expr( &mut self, list: &expr::List ) -> Json {
+    node( "List", vec![
+      ( "line", line( list.bracket.line ) ),
+      ( "elements", self.exprs( &list.elements ) )
+    ] )
+  }
+  fn visit_literal_expr( &mut self, literal: &expr::Literal ) -> Json {
+    let value = match literal.value.token_type {
+
+      // re-printed, as JSON won't take every number Lox does [ e.g. "007" ]
+      TokenType::Number => Json::Number( literal.value.lexeme.parse::<f64>().unwrap_or( 0.0 ).to_string() ),
+      TokenType::String => Json::String( literal.value.lexeme.clone() ),
+      TokenType::True => Json::Bool( true ),
+      TokenType::False => Json::Bool( false ),
+      _ => Json::Null
+    };
+    node( "Literal", vec![
+      ( "line", line( literal.value.line ) ),
+      ( "value", value )
+    ] )
+  }
+  fn visit_map_expr( &mut self, map: &expr::Map ) -> Json {
+    let entries = map.entries.iter().map( | ( key, value ) | Json::Object( vec![
+      ( "key", self.expr( key ) ),
+      ( "value", self.expr( value ) )
+    ] ) ).collect();
+    node( "Map", vec![
+      ( "line", line( map.brace.line ) ),
+      ( "entries", Json::Array( entries ) )
+    ] )
+  }
+  fn visit_set_index_expr( &mut self, set_index: &expr::SetIndex ) -> Json {
+    node( "SetIndex", vec![
+      ( "line", line( set_index.bracket.line ) ),
+      ( "object", self.expr( &set_index.object ) ),
+      ( "index", self.expr( &set_index.index ) ),
+      ( "value", self.expr( &set_index.value ) )
+    ] )
+  }
+  fn visit_unary_expr( &mut self, unary: &expr::Unary ) -> Json {
+    node( "Unary", vec![
+      ( "operator", name( &unary.operator ) ),
+      ( "line", line( unary.operator.line ) ),
+      ( "right", self.expr( &unary.right ) )
+    ] )
+  }
+  fn visit_variable_expr( &mut self, variable: &expr::Variable ) -> Json {
+    node( "Variable", vec![
+      ( "name", name( &variable.name ) ),
+      ( "line", line( variable.name.line ) ),
+      ( "jump", Json::Number( variable.jump.to_string() ) )
+    ] )
+  }
+}
+
+impl Json {
+  fn write( &self, out: &mut String, indent: usize ) {
+    match self {
+      Json::Null => *out += "null",
+      Json::Bool( b ) => *out += &b.to_string(),
+      Json::Number( n ) => *out += n,
+      Json::String( s ) => write_string( out, s ),
+      Json::Array( items ) if items.is_empty() => *out += "[]",
+      Json::Array( items ) => {
+        *out += "[\n";
+        for ( idx, item ) in items.iter().enumerate() {
+          *out += &"  ".repeat( indent + 1 );
+          item.write( out, indent + 1 );
+          *out += if idx + 1 < items.len() { ",\n" } else { "\n" };
+        }
+        *out += &"  ".repeat( indent );
+        *out += "]";
+      },
+      Json::Object( fields ) => {
+        *out += "{\n";
+        for ( idx, ( key, value ) ) in fields.iter().enumerate() {
+          *out += &"  ".repeat( indent + 1 );
+          write_string( out, key );
+          *out += ": ";
+          value.write( out, indent + 1 );
+          *out += if idx + 1 < fields.len() { ",\n" } else { "\n" };
+        }
+        *out += &"  ".repeat( indent );
+        *out += "}";
+      }
+    }
+  }
+}
+
+// an object whose first field names what kind of node it is
+fn node( kind: &str, mut fields: Vec<( &'static str, Json )> ) -> Json {
+  fields.insert( 0, ( "type", Json::String( kind.into() ) ) );
+  Json::Object( fields )
+}
+fn name( token: &Token ) -> Json {
+  Json::String( token.lexeme.clone() )
+}
+fn line( line: u32 ) -> Json {
+  Json::Number( line.to_string() )
+}
+fn write_string( out: &mut String, s: &str ) {
+  out.push( '"' );
+  for c in s.chars() {
+    match c {
+      '"' => *out += "\\\"",
+      '\\' => *out += "\\\\",
+      '\n' => *out += "\\n",
+      '\r' => *out += "\\r",
+      '\t' => *out += "\\t",
+      c if ( c as u32 ) < 0x20 => *out += &format!( "\\u{:04x}", c as u32 ),
+      c => out.push( c )
+    }
+  }
+  out.push( '"' );
+}
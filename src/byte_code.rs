@@ -1,6 +1,8 @@
-use std::{any::Any, process::exit};
+use std::{any::Any, collections::{BTreeMap, HashMap, HashSet}, fmt::{Debug, Display}, io::{self, Write}, process::exit, rc::Rc,
+  sync::{atomic::AtomicBool, Arc}};
 
-use crate::{error::Error, eval::Eval, expr::{self}, stmt::{self, Stmt}, token::TokenType, util::{assert, Stack}};
+use crate::{error::Error, eval::Eval, expr::{self}, interpreter::DEFAULT_MAX_DEPTH, native::Arity, prelude::define_vm_prelude, stmt::{self, Stmt}, lists::{vm_get_index, vm_list_method, vm_set_index},
+  maps::{vm_get_key, vm_map_method, vm_set_key, MapKey}, strings::{vm_char_at, vm_string_method}, token::{Token, TokenType}, util::{Fuel, Memory, RcMut, Stack}};
 
 #[derive(Debug, Clone)]
 enum Op {
@@ -9,10 +11,29 @@ enum Op {
   Mul,
   Div,
   Neg,
+  Not,
+  Equal,
+  Less,
+  Greater,
   Pop,
   PushConstant( u8 ),
   Dup( u8 ),
+  SetLocal( u8 ),
   Print,
+  GetGlobal( u8 ),
+  SetGlobal( u8 ),
+  GetUpvalue( u8 ),
+  SetUpvalue( u8 ),
+  CloseUpvalue,
+  Closure( u8 ),
+  Throw,
+  Rethrow,
+  Call( u8 ),
+  BuildList( u8 ),
+  BuildMap( u8 ),
+  GetIndex,
+  SetIndex,
+  GetProperty( u8 ),
   And,
   Or,
   Return,
@@ -23,60 +44,498 @@ enum Op {
 
 pub struct ByteCode {
   code: Vec<Op>,
+
+  // the source line of each op
+  lines: Vec<u32>,
   constants: Vec<Value>,
+  functions: Vec<Rc<FunctionProto>>,
+  handlers: Vec<Handler>,
+
+  // the variables declared at the top level of the script, by stack slot, with the op their declaration ends before
+  script_locals: Vec<( String, usize )>
+}
+
+/// An exception handler: exceptions raised by code in `start..end` jump to
+/// `target`, with the stack cut back to `stack_size` and the exception pushed.
+///
+/// Nested handlers come first, so the first match is the innermost one.
+#[derive(Debug, Clone)]
+struct Handler {
+
+  // the entry of the function the code belongs to [ None for the script ]
+  owner: Option<usize>,
+  start: usize,
+  end: usize,
+  target: usize,
+  stack_size: usize,
+
+  // whether the target is a finally block, which rethrows once it's done
+  finally: bool
 }
 
 pub struct Compiler {
   code: Vec<Op>,
+  lines: Vec<u32>,
+
+  // the source line of the ops being emitted
+  line: u32,
   constants: Vec<Value>,
-  locals: Vec<String>,
+  functions: Vec<Rc<FunctionProto>>,
+  handlers: Vec<Handler>,
+  entry: Option<usize>,
+  locals: Vec<Local>,
+  upvalues: Vec<Capture>,
   stack_size: usize,
+  loops: Vec<Loop>,
+  finallies: Vec<stmt::Block>,
+  enclosing: Vec<FunctionScope>,
+  errors: Vec<Error>,
   had_error: bool
 }
 
+struct Local {
+  name: String,
+
+  // whether a closure captures it, so leaving its scope must close the upvalue
+  captured: bool,
+
+  // the op its declaration ends before, from which on its slot holds it
+  defined: usize
+}
+
+/// The compiler's state for a function whose body is interrupted by a nested one.
+struct FunctionScope {
+  entry: Option<usize>,
+  locals: Vec<Local>,
+  upvalues: Vec<Capture>,
+  stack_size: usize,
+  loops: Vec<Loop>,
+  finallies: Vec<stmt::Block>
+}
+
+/// A compiled function. Its code lives in the shared code vector, starting at `entry`.
+#[derive(Debug)]
+pub struct FunctionProto {
+  name: String,
+  arity: usize,
+  entry: usize,
+  captures: Vec<Capture>
+}
+
+/// Where a closure finds a captured variable when it's created: a local slot of
+/// the enclosing function, or one of the enclosing function's own upvalues.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Capture {
+  is_local: bool,
+  index: u8
+}
+
+pub struct Closure {
+  proto: Rc<FunctionProto>,
+  upvalues: Vec<RcMut<Upvalue>>
+}
+
+/// A captured variable: still on the stack while its scope is live, then moved
+/// into the upvalue when the scope ends.
 #[derive(Debug, Clone)]
-enum Value {
+pub enum Upvalue {
+  Open( usize ),
+  Closed( Value )
+}
+
+struct Frame {
+  closure: Rc<Closure>,
+  return_ip: usize,
+
+  // the stack slot holding the callee, followed by the arguments and locals
+  base: usize
+}
+
+/// The jumps out of the loop being compiled, patched once its end is known.
+struct Loop {
+  breaks: Vec<usize>,
+  continues: Vec<usize>,
+
+  // the stack size on entering the body, so 'break' and 'continue' can pop the locals above it
+  stack_size: usize,
+
+  // how many finally blocks were active outside the loop, so the rest run on 'break' and 'continue'
+  finally_depth: usize
+}
+
+#[derive(Debug, Clone)]
+pub enum Value {
   Number( f64 ),
   Boolean( bool ),
+  String( String ),
+  List( RcMut<Vec<Value>> ),
+  Map( RcMut<BTreeMap<MapKey, Value>> ),
+  Native( Rc<NativeFunction> ),
+  Method( Box<Value>, Rc<NativeFunction> ),
+  Closure( Rc<Closure> ),
+  Module( Rc<VmModule> ),
   Nil
 }
 
+/// The byte code counterpart of `eval::Module`: a named set of values reached with `.`.
+#[derive(Debug)]
+pub struct VmModule {
+  pub name: String,
+  pub members: HashMap<String, Value>
+}
+
+/// The byte code counterpart of `native::Native`: a Rust function callable from scripts.
+pub struct NativeFunction {
+  name: String,
+  arity: Arity,
+  fun: fn( &mut Vm, &[Value] ) -> Result<Value, Error>
+}
+
 pub struct Vm {
   bc: ByteCode,
   ip: usize,
   stack: Stack<Value>,
+  frames: Vec<Frame>,
+  open_upvalues: Vec<RcMut<Upvalue>>,
+
+  // the exception being raised, and the error to report if nothing catches it
+  raised: Option<( Value, Error )>,
+
+  // the errors held by finally blocks until they rethrow, with the frame depth of each
+  unwinding: Vec<( usize, Error )>,
+  globals: HashMap<String, Value>,
+  max_depth: usize,
+
+  // spent one unit per instruction
+  fuel: Fuel,
+  memory: Memory,
+
+  // where print statements write to [ stdout unless the host says otherwise ]
+  out: Box<dyn Write>,
+
+  // the code the script asked to exit with, leaving it to the host to act on
+  exit_code: Option<i32>,
+
+  // the op the script's top level stopped on, its closing return if it ran to the end
+  reached: usize,
+  trace: bool,
+  errors: Vec<Error>,
   had_error: bool
 }
 
 impl Value {
   pub fn is_truthy( &self ) -> bool {
     match self {
-      Value::Number( _ ) => false,
+      Value::Number( _ ) => true,
       Value::Boolean( b ) => *b,
+      Value::String( _ ) => true,
+      Value::List( _ ) => true,
+      Value::Map( _ ) => true,
+      Value::Native( _ ) => true,
+      Value::Method( _, _ ) => true,
+      Value::Closure( _ ) => true,
+      Value::Module( _ ) => true,
       Value::Nil => false,
     }
   }
+  pub fn get_type_name( &self ) -> String {
+    match self {
+      Value::Number( _ ) => "Number".to_string(),
+      Value::Boolean( _ ) => "Bool".to_string(),
+      Value::String( _ ) => "String".to_string(),
+      Value::List( _ ) => "List".to_string(),
+      Value::Map( _ ) => "Map".to_string(),
+      Value::Native( native ) => format!( "native<{}>", native.arity ),
+      Value::Method( _, native ) => format!( "native<{}>", native.arity ),
+      Value::Closure( closure ) => format!( "fun<{}>", closure.proto.arity ),
+      Value::Module( _ ) => "Module".to_string(),
+      Value::Nil => "Nil".to_string()
+    }
+  }
+
+  /// The bytes this value holds beyond its own slot [ elements are counted when they're made ].
+  pub fn footprint( &self ) -> usize {
+    match self {
+      Value::String( s ) => s.len(),
+      Value::List( list ) => list.view().len() * std::mem::size_of::<Value>(),
+      Value::Map( map ) => map.view().len() * ( std::mem::size_of::<MapKey>() + std::mem::size_of::<Value>() ),
+      _ => 0
+    }
+  }
+  pub fn repr( &self ) -> String {
+    match self {
+      Value::String( s ) => format!( "\"{}\"", s ),
+      _ => self.to_string()
+    }
+  }
+}
+
+impl Display for Value {
+  fn fmt( &self, f: &mut std::fmt::Formatter<'_> ) -> std::fmt::Result {
+    match self {
+      Value::Number( x ) => write!( f, "{}", x ),
+      Value::Boolean( b ) => write!( f, "{}", b ),
+      Value::String( s ) => write!( f, "{}", s ),
+      Value::List( list ) => {
+        let elements: Vec<String> = list.view().iter().map( | v | v.repr() ).collect();
+        write!( f, "[{}]", elements.join( ", " ) )
+      },
+      Value::Map( map ) => {
+        let entries: Vec<String> = map.view().iter()
+          .map( | ( key, value ) | format!( "{}: {}", key, value.repr() ) ).collect();
+        write!( f, "{{{}}}", entries.join( ", " ) )
+      },
+      Value::Native( native ) => write!( f, "{:?}", native ),
+      Value::Method( receiver, native ) => write!( f, "<method {}.{}<{}>()>",
+        receiver.get_type_name(), native.name, native.arity ),
+      Value::Closure( closure ) => write!( f, "{:?}", closure ),
+      Value::Module( module ) => write!( f, "<module {}>", module.name ),
+      Value::Nil => write!( f, "nil" )
+    }
+  }
+}
+
+impl NativeFunction {
+  pub fn new( name: &str, arity: Arity, fun: fn( &mut Vm, &[Value] ) -> Result<Value, Error> ) -> NativeFunction {
+    NativeFunction {
+      name: name.into(),
+      arity,
+      fun
+    }
+  }
+  pub fn name( &self ) -> &str {
+    &self.name
+  }
+}
+
+impl Debug for NativeFunction {
+  fn fmt( &self, f: &mut std::fmt::Formatter<'_> ) -> std::fmt::Result {
+    write!( f, "<native {}<{}>()>", self.name, self.arity )
+  }
+}
+
+// not derived, since a closure may capture itself
+impl Debug for Closure {
+  fn fmt( &self, f: &mut std::fmt::Formatter<'_> ) -> std::fmt::Result {
+    write!( f, "{}<{}>()", self.proto.name, self.proto.arity )
+  }
+}
+
+impl Local {
+  fn new( name: &str ) -> Local {
+    Local { name: name.into(), captured: false, defined: 0 }
+  }
 }
 
 impl Vm {
   pub fn new( bc: ByteCode ) -> Vm {
-    Vm {
+    let mut vm = Vm {
       bc,
       ip: 0,
       stack: Stack::new(),
+      frames: Vec::new(),
+      open_upvalues: Vec::new(),
+      raised: None,
+      unwinding: Vec::new(),
+      globals: HashMap::new(),
+      max_depth: DEFAULT_MAX_DEPTH,
+      fuel: Fuel::new(),
+      memory: Memory::new(),
+      out: Box::new( io::stdout() ),
+      exit_code: None,
+      reached: 0,
+      trace: false,
+      errors: Vec::new(),
       had_error: false
+    };
+    define_vm_prelude( &mut vm );
+    vm
+  }
+  pub fn take_errors( &mut self ) -> Vec<Error> {
+    std::mem::take( &mut self.errors )
+  }
+
+  /// Stops the running script once the current native call returns, as `exit( code )` does.
+  pub fn exit( &mut self, code: i32 ) {
+    self.exit_code = Some( code );
+  }
+
+  /// The code the last run asked to exit with, if it did.
+  pub fn exit_code( &self ) -> Option<i32> {
+    self.exit_code
+  }
+  fn runtime_error( &mut self, msg: String ) {
+    self.raise( Error::from_msg( msg ) );
+  }
+
+  /// Raises `error` as an exception, which scripts catch as a Map of its message and line.
+  fn raise( &mut self, error: Error ) {
+    let error = self.locate( error );
+    self.raised = Some( ( error_value( &error ), error ) );
+  }
+
+  /// Places an error without a line of its own at the current op, and attaches the calls in progress.
+  fn locate( &self, mut error: Error ) -> Error {
+    if error.line == 0 && error.loc.is_empty() {
+      error.line = self.bc.lines[ self.ip ];
+    }
+    let calls: Vec<( String, u32 )> = self.frames.iter()
+      .map( | frame | ( frame.closure.proto.name.clone(), self.bc.lines[ frame.return_ip - 1 ] ) )
+      .collect();
+    error.with_trace( &calls )
+  }
+  pub fn define_global( &mut self, name: &str, value: Value ) {
+    self.globals.insert( name.into(), value );
+  }
+  pub fn define_native( &mut self, name: &str, arity: Arity, fun: fn( &mut Vm, &[Value] ) -> Result<Value, Error> ) {
+    self.define_global( name, Value::Native( Rc::new( NativeFunction::new( name, arity, fun ) ) ) );
+  }
+
+  /// Limits how many frames may be active at once, so runaway recursion fails instead of growing the stack unbounded.
+  pub fn set_max_depth( &mut self, max_depth: usize ) {
+    self.max_depth = max_depth;
+  }
+
+  /// Limits each run to `budget` instructions [ None for no limit ].
+  pub fn set_budget( &mut self, budget: Option<u64> ) {
+    self.fuel.set_limit( budget );
+  }
+
+  /// A flag that, once set from any thread, stops the script at its next instruction.
+  pub fn interrupt_handle( &self ) -> Arc<AtomicBool> {
+    self.fuel.interrupt_handle()
+  }
+
+  /// Caps the bytes each run may hold in strings, collections and closures [ None for the built-in ceiling ].
+  pub fn set_memory_limit( &mut self, limit: Option<usize> ) {
+    self.memory.set_limit( limit );
+  }
+
+  /// Makes sure `bytes` more fit under the memory cap, for natives that build large values in one go.
+  ///
+  /// Nothing is charged: the call charges the value the native returns.
+  pub fn make_room( &mut self, bytes: usize ) -> Result<(), Error> {
+    if bytes > self.memory.room() {
+      let live = self.live_size();
+      self.memory.settle( live );
+      if bytes > self.memory.room() {
+        return Err( Error::from_msg( "Out of memory.".into() ) );
+      }
     }
+    Ok( () )
+  }
+
+  /// Charges a native's work against the budget before it does it, failing if that runs the budget out.
+  pub fn spend( &mut self, steps: u64 ) -> Result<(), Error> {
+    match self.fuel.burn( steps ) {
+      Some( reason ) => Err( Error::from_msg( reason.into() ) ),
+      None => Ok( () )
+    }
+  }
+
+  /// Sends the output of print statements to `out`.
+  pub fn set_output( &mut self, out: Box<dyn Write> ) {
+    self.out = out;
+  }
+  pub fn flush_output( &mut self ) {
+    let _ = self.out.flush();
+  }
+  pub fn set_trace( &mut self, trace: bool ) {
+    self.trace = trace;
   }
   pub fn exec( &mut self ) -> ( Eval, bool ) {
-    let result = Eval::Nil;
+    self.start_run();
+    self.run();
+    self.reached = match self.frames.first() {
+      Some( frame ) => frame.return_ip - 1,
+      None => self.ip
+    };
+    ( Eval::Nil, self.had_error )
+  }
+
+  /// Calls `callee` with `args` once the script has run, as a host would, returning what it returns.
+  pub fn call_function( &mut self, callee: Value, args: Vec<Value> ) -> ( Value, bool ) {
+    self.start_run();
+
+    // the call comes back to the script's closing return, which then stops the run
+    // [ a run that failed leaves its frames and handlers behind ]
+    let end = self.bc.code.len() - 1;
+    self.ip = end;
+    self.frames.clear();
+    self.unwinding.clear();
+    let depth = self.stack.depth();
+    let arg_count = args.len() as u8;
+    self.stack.push( callee );
+    for arg in args {
+      self.stack.push( arg );
+    }
+    let ( _, stop ) = self.exec_op( Op::Call( arg_count ) );
+    if stop {
+      if let Some( ( _, error ) ) = self.raised.take() {
+        self.errors.push( error );
+      }
+      self.had_error = self.exit_code.is_none();
+    } else if !self.frames.is_empty() {
+      self.frames.last_mut().unwrap().return_ip = end;
+      self.run();
+    }
+    self.ip = end;
+    let result = if self.had_error || self.stack.depth() <= depth { Value::Nil } else { self.stack.pop() };
+    self.stack.truncate( depth );
+    self.flush_output();
+    ( result, self.had_error )
+  }
+
+  /// A variable declared at the top level of the script, once the script's run got past its declaration, or else a global.
+  ///
+  /// The VM keeps the former on the stack, in the slot the compiler gave it.
+  pub fn global( &self, name: &str ) -> Option<Value> {
+    let slot = self.bc.script_locals.iter().rposition( | ( local, _ ) | local == name );
+    match slot {
+      Some( slot ) if self.bc.script_locals[ slot ].1 <= self.reached && slot < self.stack.depth() => {
+        Some( self.stack.get( slot ).clone() )
+      },
+      _ => self.globals.get( name ).cloned()
+    }
+  }
+
+  // each run starts afresh, but for the globals
+  fn start_run( &mut self ) {
+    self.had_error = false;
+    self.exit_code = None;
+    self.fuel.refill();
+    self.memory.reset();
+  }
+
+  /// Executes from the current op until the script returns, stops or fails.
+  fn run( &mut self ) {
     loop {
+
+      // running out stops the script outright, past any handlers
+      if let Some( reason ) = self.fuel.burn( 1 ) {
+        let error = self.locate( Error::from_msg( reason.into() ) );
+        self.errors.push( error );
+        self.had_error = true;
+        break;
+      }
       let op = self.bc.code.get( self.ip ).unwrap().clone();
       let ( ip_offset, stop ) = self.exec_op( op.clone() );
-      println!( "\n\n{:?}\n============", op );
-      for i in 0..self.stack.depth() {
-        println!( "{:?}", self.stack.peek( i ) );
+      if self.trace {
+        eprintln!( "\n\n{:?}\n============", op );
+        for i in 0..self.stack.depth() {
+          eprintln!( "{:?}", self.stack.peek( i ) );
+        }
       }
       if stop {
+
+        // an exception unwinds to the nearest handler, if there is one
+        if let Some( ( exception, error ) ) = self.raised.take() {
+          if self.unwind( exception, &error ) {
+            continue;
+          }
+          self.errors.push( error );
+          self.had_error = true;
+        }
         // result = Eval::Number( *self.stack.peek( 0 ) );
         break;
       }
@@ -86,8 +545,7 @@ impl Vm {
         self.ip += ip_offset as usize;
       }
     }
-
-    ( result, self.had_error )
+    self.flush_output();
   }
   fn exec_op( &mut self, op: Op ) -> ( i16, bool ) {
     let mut ip_offset = 1i16;
@@ -100,8 +558,14 @@ impl Vm {
           ( Value::Number( x ), Value::Number( y ) ) => {
             *self.stack.peek_mut( 0 ) = Value::Number( x + y );
           },
+          ( Value::String( x ), Value::String( y ) ) => {
+            if !self.allocate( x.len() + y.len() ) {
+              return ( ip_offset, true );
+            }
+            *self.stack.peek_mut( 0 ) = Value::String( x.to_owned() + y );
+          },
           _ => {
-            eprintln!( "\nUnknown operation '+' on types {:?} and {:?}.", left, right );
+            self.raise( Vm::operand_error( &left, &right ) );
             stop = true;
           }
         }
@@ -114,7 +578,7 @@ impl Vm {
             *self.stack.peek_mut( 0 ) = Value::Number( x - y );
           },
           _ => {
-            eprintln!( "\nUnknown operation '-' on types {:?} and {:?}.", left, right );
+            self.raise( Vm::operand_error( &left, &right ) );
             stop = true;
           }
         }
@@ -127,7 +591,7 @@ impl Vm {
             *self.stack.peek_mut( 0 ) = Value::Number( x * y );
           },
           _ => {
-            eprintln!( "\nUnknown operation '*' on types {:?} and {:?}.", left, right );
+            self.raise( Vm::operand_error( &left, &right ) );
             stop = true;
           }
         }
@@ -140,7 +604,7 @@ impl Vm {
             *self.stack.peek_mut( 0 ) = Value::Number( x / y );
           },
           _ => {
-            eprintln!( "\nUnknown operation '/' on types {:?} and {:?}.", left, right );
+            self.raise( Vm::operand_error( &left, &right ) );
             stop = true;
           }
         }
@@ -150,10 +614,20 @@ impl Vm {
         if let Value::Number( x ) = right {
           *self.stack.peek_mut( 0 ) = Value::Number( -x );
         } else {
-          eprintln!( "\nUnknown operation '-' on type {:?}.", right );
+          self.runtime_error( format!( "Unary '-' cannot be applied to a value of type {}.", right.get_type_name() ) );
           stop = true;
         }
       }
+      Op::Not => {
+        let right = self.stack.peek( 0 ).clone();
+        *self.stack.peek_mut( 0 ) = Value::Boolean( !right.is_truthy() );
+      },
+      Op::Equal | Op::Less | Op::Greater => {
+        let right = self.stack.pop();
+        let left = self.stack.pop();
+        let result = Vm::compare( &op, &left, &right ).map( Value::Boolean );
+        stop = !self.push_result( result );
+      },
       Op::PushConstant( idx ) => {
         self.stack.push( self.bc.constants.get( idx as usize ).unwrap().clone() );
       },
@@ -168,7 +642,21 @@ impl Vm {
         *self.stack.peek_mut( 0 ) = Value::Boolean( left.is_truthy() || right.is_truthy() );
       },
       Op::Return => {
-        stop = true;
+        match self.frames.pop() {
+
+          // the end of the script
+          None => stop = true,
+
+          // a function call: swap its frame on the stack for the result
+          Some( frame ) => {
+            let result = self.stack.pop();
+            self.close_upvalues( frame.base );
+            self.stack.truncate( frame.base );
+            self.stack.push( result );
+            self.ip = frame.return_ip;
+            ip_offset = 0;
+          }
+        }
       },
       Op::Pop => {
         self.stack.pop();
@@ -177,8 +665,236 @@ impl Vm {
         let local = self.stack.peek( depth as usize );
         self.stack.push( local.clone() );
       },
+      Op::SetLocal( depth ) => {
+        let value = self.stack.peek( 0 ).clone();
+        *self.stack.peek_mut( depth as usize ) = value;
+      },
+      Op::SetGlobal( idx ) => {
+        let name = match self.bc.constants.get( idx as usize ).unwrap() {
+          Value::String( name ) => name.clone(),
+          _ => unreachable!( "Internal error: Global names are always string constants." )
+        };
+        if self.globals.contains_key( &name ) {
+          self.globals.insert( name, self.stack.peek( 0 ).clone() );
+        } else {
+          self.runtime_error( format!( "Undefined global '{}'.", name ) );
+          stop = true;
+        }
+      },
+      Op::GetUpvalue( idx ) => {
+        let value = match &*self.upvalue( idx ).view() {
+          Upvalue::Open( slot ) => self.stack.get( *slot ).clone(),
+          Upvalue::Closed( value ) => value.clone()
+        };
+        self.stack.push( value );
+      },
+      Op::SetUpvalue( idx ) => {
+        let value = self.stack.peek( 0 ).clone();
+        let mut upvalue = self.upvalue( idx );
+        let mut upvalue = upvalue.view_mut();
+        match &mut *upvalue {
+          Upvalue::Open( slot ) => *self.stack.get_mut( *slot ) = value,
+          Upvalue::Closed( closed ) => *closed = value
+        }
+      },
+      Op::CloseUpvalue => {
+        self.close_upvalues( self.stack.depth() - 1 );
+        self.stack.pop();
+      },
+      Op::Closure( idx ) => {
+        let proto = self.bc.functions.get( idx as usize ).unwrap().clone();
+        if !self.allocate( proto.captures.len() * std::mem::size_of::<Upvalue>() ) {
+          return ( ip_offset, true );
+        }
+        let base = self.frame_base();
+        let mut upvalues: Vec<RcMut<Upvalue>> = Vec::new();
+        for capture in &proto.captures {
+          upvalues.push( if capture.is_local {
+            self.capture_upvalue( base + capture.index as usize )
+          } else {
+            self.upvalue( capture.index )
+          } );
+        }
+        self.stack.push( Value::Closure( Rc::new( Closure { proto, upvalues } ) ) );
+      },
+      Op::Throw => {
+        let exception = self.stack.pop();
+        let error = Error {
+          line: self.bc.lines[ self.ip ],
+          loc: " at 'throw'".into(),
+          msg: format!( "Uncaught exception: {}", exception.repr() ),
+          trace: Vec::new()
+        };
+        self.raised = Some( ( exception, self.locate( error ) ) );
+        stop = true;
+      },
+      Op::Rethrow => {
+
+        // errors held by frames that have since returned are stale
+        let depth = self.frames.len();
+        while self.unwinding.last().is_some_and( | ( d, _ ) | *d > depth ) {
+          self.unwinding.pop();
+        }
+        let exception = self.stack.pop();
+        let ( _, error ) = self.unwinding.pop()
+          .expect( "Internal error: A finally block only rethrows what it caught." );
+        self.raised = Some( ( exception, error ) );
+        stop = true;
+      },
+      Op::GetGlobal( idx ) => {
+        let name = self.bc.constants.get( idx as usize ).unwrap().clone();
+        match name {
+          Value::String( name ) => match self.globals.get( &name ) {
+            Some( value ) => self.stack.push( value.clone() ),
+            None => {
+              self.runtime_error( format!( "Undefined global '{}'.", name ) );
+              stop = true;
+            }
+          },
+          _ => unreachable!( "Internal error: Global names are always string constants." )
+        }
+      },
+      Op::Call( arg_count ) => {
+
+        // closures run in place, their arguments becoming the first locals
+        if let Value::Closure( closure ) = self.stack.peek( arg_count as usize ).clone() {
+          if closure.proto.arity != arg_count as usize {
+            self.runtime_error( format!( "Expected {} arguments to function call, but found {}.",
+              closure.proto.arity, arg_count ) );
+            return ( ip_offset, true );
+          }
+          if self.frames.len() >= self.max_depth {
+            self.runtime_error( "Stack overflow.".into() );
+            return ( ip_offset, true );
+          }
+          self.frames.push( Frame {
+            closure: closure.clone(),
+            return_ip: self.ip + 1,
+            base: self.stack.depth() - arg_count as usize - 1
+          } );
+          self.ip = closure.proto.entry;
+          return ( 0, false );
+        }
+
+        let mut args: Vec<Value> = Vec::new();
+        for _ in 0..arg_count {
+          args.push( self.stack.pop() );
+        }
+        args.reverse();
+        let callee = self.stack.pop();
+        match callee {
+          Value::Native( native ) => {
+            stop = !self.call_native( &native, args, None );
+          },
+          Value::Method( receiver, native ) => {
+            stop = !self.call_native( &native, args, Some( *receiver ) );
+          },
+          _ => {
+            self.runtime_error( format!( "Cannot call a {}.", callee.get_type_name() ) );
+            stop = true;
+          }
+        }
+      },
+      Op::BuildList( count ) => {
+        if !self.allocate( count as usize * std::mem::size_of::<Value>() ) {
+          return ( ip_offset, true );
+        }
+        let mut elements: Vec<Value> = Vec::new();
+        for _ in 0..count {
+          elements.push( self.stack.pop() );
+        }
+        elements.reverse();
+        self.stack.push( Value::List( RcMut::new( elements ) ) );
+      },
+      Op::BuildMap( count ) => {
+        if !self.allocate( count as usize * ( std::mem::size_of::<MapKey>() + std::mem::size_of::<Value>() ) ) {
+          return ( ip_offset, true );
+        }
+        let mut entries: Vec<( Value, Value )> = Vec::new();
+        for _ in 0..count {
+          let value = self.stack.pop();
+          let key = self.stack.pop();
+          entries.push( ( key, value ) );
+        }
+        entries.reverse();
+        let mut map: BTreeMap<MapKey, Value> = BTreeMap::new();
+        let mut result = Ok( () );
+        for ( key, value ) in entries {
+          match MapKey::from_value( &key ) {
+            Ok( key ) => { map.insert( key, value ); },
+            Err( e ) => {
+              result = Err( e );
+              break;
+            }
+          }
+        }
+        stop = !self.push_result( result.map( | _ | Value::Map( RcMut::new( map ) ) ) );
+      },
+      Op::GetIndex => {
+        let index = self.stack.pop();
+        let object = self.stack.pop();
+        let result = match &object {
+          Value::List( list ) => vm_get_index( list, &index ),
+          Value::Map( map ) => vm_get_key( map, &index ),
+          Value::String( s ) => vm_char_at( s, &index ),
+          _ => Err( Error::from_msg( format!( "A value of type {} cannot be indexed.", object.get_type_name() ) ) )
+        };
+        stop = !self.push_result( result );
+      },
+      Op::SetIndex => {
+        let value = self.stack.pop();
+        let index = self.stack.pop();
+        let object = self.stack.pop();
+        let before = object.footprint();
+        let result = match &object {
+          Value::List( list ) => vm_set_index( list, &index, value ),
+          Value::Map( map ) => vm_set_key( map, &index, value ),
+          _ => Err( Error::from_msg( format!( "A value of type {} cannot be indexed.", object.get_type_name() ) ) )
+        };
+
+        // a new key grows a map
+        if result.is_ok() && !self.allocate( object.footprint().saturating_sub( before ) ) {
+          return ( ip_offset, true );
+        }
+        stop = !self.push_result( result );
+      },
+      Op::GetProperty( idx ) => {
+        let name = match self.bc.constants.get( idx as usize ).unwrap() {
+          Value::String( name ) => name.clone(),
+          _ => unreachable!( "Internal error: Property names are always string constants." )
+        };
+        let object = self.stack.pop();
+        let result = match &object {
+          Value::List( _ ) => match vm_list_method( &name ) {
+            Some( native ) => Ok( Value::Method( Box::new( object.clone() ), Rc::new( native ) ) ),
+            None => Err( Error::from_msg( format!( "Lists have no method '{}'.", name ) ) )
+          },
+          Value::Map( map ) => match vm_map_method( &name ) {
+            Some( native ) => Ok( Value::Method( Box::new( object.clone() ), Rc::new( native ) ) ),
+
+            // otherwise, m.name reads m[ "name" ], as in the tree-walk interpreter
+            None => match map.view().get( &MapKey::String( name.clone() ) ) {
+              Some( member ) => Ok( member.clone() ),
+              None => Err( Error::from_msg( format!( "Maps have no method or key '{}'.", name ) ) )
+            }
+          },
+          Value::String( _ ) => match vm_string_method( &name ) {
+            Some( native ) => Ok( Value::Method( Box::new( object.clone() ), Rc::new( native ) ) ),
+            None => Err( Error::from_msg( format!( "Strings have no method '{}'.", name ) ) )
+          },
+          Value::Module( module ) => match module.members.get( &name ) {
+            Some( member ) => Ok( member.clone() ),
+            None => Err( Error::from_msg( format!( "Module '{}' has no member '{}'.", module.name, name ) ) )
+          },
+          _ => Err( Error::from_msg( format!( "A value of type {} has no properties.", object.get_type_name() ) ) )
+        };
+        stop = !self.push_result( result );
+      },
       Op::Print => {
-        println!( "[Execution output: '{:?}']", self.stack.peek( 0 ) );
+        if let Err( e ) = writeln!( self.out, "{}", self.stack.peek( 0 ) ) {
+          self.runtime_error( format!( "Error writing output: {}", e ) );
+          stop = true;
+        }
       },
       Op::Nop => {},
       Op::JumpIfFalse( delta ) => {
@@ -192,35 +908,299 @@ impl Vm {
     }
     ( ip_offset, stop )
   }
+
+  fn frame_base( &self ) -> usize {
+    self.frames.last().map_or( 0, | frame | frame.base )
+  }
+  fn upvalue( &self, idx: u8 ) -> RcMut<Upvalue> {
+    let frame = self.frames.last().expect( "Internal error: Upvalues are only used inside functions." );
+    frame.closure.upvalues.get( idx as usize ).unwrap().clone()
+  }
+
+  /// Shares the open upvalue for the stack `slot`, so closures capturing the same variable see each other's writes.
+  fn capture_upvalue( &mut self, slot: usize ) -> RcMut<Upvalue> {
+    for upvalue in &self.open_upvalues {
+      if let Upvalue::Open( open ) = *upvalue.view() {
+        if open == slot {
+          return upvalue.clone();
+        }
+      }
+    }
+    let upvalue = RcMut::new( Upvalue::Open( slot ) );
+    self.open_upvalues.push( upvalue.clone() );
+    upvalue
+  }
+
+  /// Moves the variables in stack slots `from` and up into the upvalues capturing them.
+  fn close_upvalues( &mut self, from: usize ) {
+    let mut still_open: Vec<RcMut<Upvalue>> = Vec::new();
+    for mut upvalue in std::mem::take( &mut self.open_upvalues ) {
+      let slot = match *upvalue.view() {
+        Upvalue::Open( slot ) => slot,
+        Upvalue::Closed( _ ) => unreachable!( "Internal error: Closed upvalues aren't kept with the open ones." )
+      };
+      if slot >= from {
+        *upvalue.view_mut() = Upvalue::Closed( self.stack.get( slot ).clone() );
+      } else {
+        still_open.push( upvalue );
+      }
+    }
+    self.open_upvalues = still_open;
+  }
+
+  /// Unwinds the stack to the innermost handler covering the current op, returning
+  /// from functions along the way. Returns false if no handler catches `exception`.
+  fn unwind( &mut self, exception: Value, error: &Error ) -> bool {
+    let mut ip = self.ip;
+    loop {
+      let owner = self.frames.last().map( | frame | frame.closure.proto.entry );
+      let handler = self.bc.handlers.iter()
+        .find( | h | h.owner == owner && h.start <= ip && ip < h.end )
+        .map( | h | ( h.target, h.stack_size, h.finally ) );
+      if let Some( ( target, stack_size, finally ) ) = handler {
+        if finally {
+          self.unwinding.push( ( self.frames.len(), error.clone() ) );
+        }
+        let depth = self.frame_base() + stack_size;
+        self.close_upvalues( depth );
+        self.stack.truncate( depth );
+        self.stack.push( exception );
+        self.ip = target;
+        return true;
+      }
+      match self.frames.pop() {
+        Some( frame ) => {
+          self.close_upvalues( frame.base );
+          self.stack.truncate( frame.base );
+          ip = frame.return_ip - 1;
+        },
+
+        // stopping at the top level, on the op that led here
+        None => {
+          self.ip = ip;
+          return false;
+        }
+      }
+    }
+  }
+
+  /// Evaluates the comparison `op` on `left` and `right`.
+  fn compare( op: &Op, left: &Value, right: &Value ) -> Result<bool, Error> {
+    match ( op, left, right ) {
+      ( Op::Equal, Value::Number( x ), Value::Number( y ) ) => Ok( x == y ),
+      ( Op::Equal, Value::Boolean( x ), Value::Boolean( y ) ) => Ok( x == y ),
+      ( Op::Equal, Value::String( x ), Value::String( y ) ) => Ok( x == y ),
+      ( Op::Equal, Value::Nil, Value::Nil ) => Ok( true ),
+      ( Op::Less, Value::Number( x ), Value::Number( y ) ) => Ok( x < y ),
+      ( Op::Less, Value::String( x ), Value::String( y ) ) => Ok( x < y ),
+      ( Op::Greater, Value::Number( x ), Value::Number( y ) ) => Ok( x > y ),
+      ( Op::Greater, Value::String( x ), Value::String( y ) ) => Ok( x > y ),
+      _ => Err( Vm::operand_error( left, right ) )
+    }
+  }
+
+  /// The error for a binary operation on operands it doesn't apply to, worded as the tree-walk interpreter words it.
+  fn operand_error( left: &Value, right: &Value ) -> Error {
+    Error::from_msg( format!(
+      "Unknown binary operation on the types provided. (The types are {} and {}, respectively.)",
+      left.get_type_name(), right.get_type_name() ) )
+  }
+
+  /// Accounts for `bytes` about to be allocated, raising an error if they don't fit
+  /// even once the count is settled to what the run still holds.
+  fn allocate( &mut self, bytes: usize ) -> bool {
+    if !self.memory.charge( bytes ) {
+      let live = self.live_size();
+      self.memory.settle( live );
+      if !self.memory.charge( bytes ) {
+        self.runtime_error( "Out of memory.".into() );
+        return false;
+      }
+    }
+    true
+  }
+
+  /// The bytes held by everything the run can still reach, counted as `allocate` charges them.
+  fn live_size( &self ) -> usize {
+    let mut seen: HashSet<usize> = HashSet::new();
+    let mut values: Vec<Value> = Vec::new();
+    let mut bytes = 0;
+    let roots = self.stack.iter().chain( self.globals.values() ).chain( self.raised.iter().map( | ( value, _ ) | value ) );
+    for value in roots {
+      reach( value, &mut bytes, &mut values );
+    }
+    values.extend( self.frames.iter().map( | frame | Value::Closure( frame.closure.clone() ) ) );
+    while let Some( value ) = values.pop() {
+      match &value {
+        Value::List( list ) if seen.insert( list.addr() ) => {
+          bytes += value.footprint();
+          for item in list.view().iter() {
+            reach( item, &mut bytes, &mut values );
+          }
+        },
+        Value::Map( map ) if seen.insert( map.addr() ) => {
+          bytes += value.footprint();
+          for item in map.view().values() {
+            reach( item, &mut bytes, &mut values );
+          }
+        },
+        Value::Closure( closure ) if seen.insert( Rc::as_ptr( closure ) as usize ) => {
+          bytes += closure.upvalues.len() * std::mem::size_of::<Upvalue>();
+          for upvalue in &closure.upvalues {
+
+            // open upvalues are counted where they live, on the stack
+            if let Upvalue::Closed( captured ) = &*upvalue.view() {
+              if seen.insert( upvalue.addr() ) {
+                reach( captured, &mut bytes, &mut values );
+              }
+            }
+          }
+        },
+        Value::Method( receiver, _ ) => reach( receiver, &mut bytes, &mut values ),
+        _ => {}
+      }
+    }
+    bytes
+  }
+
+  /// Pushes a successful `result`, or records its error. Returns whether execution may continue.
+  fn push_result( &mut self, result: Result<Value, Error> ) -> bool {
+    match result {
+      Ok( value ) => {
+        self.stack.push( value );
+        true
+      },
+      Err( e ) => {
+        self.raise( e );
+        false
+      }
+    }
+  }
+  fn call_native( &mut self, native: &NativeFunction, mut args: Vec<Value>, receiver: Option<Value> ) -> bool {
+    if !native.arity.accepts( args.len() ) {
+      self.runtime_error( format!( "Expected {} arguments to function call, but found {}.",
+        native.arity, args.len() ) );
+      return false;
+    }
+
+    // a method's receiver goes ahead of the arguments, but doesn't count towards the arity
+    if let Some( receiver ) = receiver {
+      args.insert( 0, receiver );
+    }
+    let before = args.first().map_or( 0, | receiver | receiver.footprint() );
+    let result = ( native.fun )( self, &args );
+    if self.exit_code.is_some() {
+      return false;
+    }
+
+    // running out stops the script outright, as between instructions
+    if let ( Err( e ), true ) = ( &result, self.fuel.stopped() ) {
+      let error = self.locate( e.clone() );
+      self.errors.push( error );
+      self.had_error = true;
+      return false;
+    }
+
+    // methods like push() grow their receiver in place
+    if let Ok( value ) = &result {
+      let grown = args.first().map_or( 0, | receiver | receiver.footprint() ).saturating_sub( before );
+      if !self.allocate( value.footprint() + grown ) {
+        return false;
+      }
+    }
+    self.push_result( result )
+  }
 }
 
 impl ByteCode {
-  fn new( code: Vec<Op>, constants: Vec<Value> ) -> ByteCode {
+  fn new( code: Vec<Op>, lines: Vec<u32>, constants: Vec<Value>, functions: Vec<Rc<FunctionProto>>,
+    handlers: Vec<Handler>, script_locals: Vec<( String, usize )> ) -> ByteCode {
     ByteCode {
       code,
-      constants
+      lines,
+      constants,
+      functions,
+      handlers,
+      script_locals
     }
   }
+  pub fn disassemble( &self ) -> String {
+    let mut result = "Code:\n".to_string();
+    for ( offset, op ) in self.code.iter().enumerate() {
+      result += &format!( "{:04} {:4} {:?}\n", offset, self.lines[ offset ], op );
+    }
+    result += "Constants:\n";
+    for ( idx, constant ) in self.constants.iter().enumerate() {
+      result += &format!( "[{}] {:?}\n", idx, constant );
+    }
+    if !self.functions.is_empty() {
+      result += "Functions:\n";
+      for ( idx, function ) in self.functions.iter().enumerate() {
+        result += &format!( "[{}] {:?}\n", idx, function );
+      }
+    }
+    if !self.handlers.is_empty() {
+      result += "Handlers:\n";
+      for handler in &self.handlers {
+        result += &format!( "{:?}\n", handler );
+      }
+    }
+    result
+  }
 }
 
 impl Compiler {
   pub fn new() -> Compiler {
     Compiler {
       code: Vec::new(),
+      lines: Vec::new(),
+      line: 0,
       constants: vec![ Value::Number( 0.0 ) ],
+      functions: Vec::new(),
+      handlers: Vec::new(),
+      entry: None,
       locals: Vec::new(),
+      upvalues: Vec::new(),
       stack_size: 0,
+      loops: Vec::new(),
+      finallies: Vec::new(),
+      enclosing: Vec::new(),
+      errors: Vec::new(),
       had_error: false
     }
   }
+  pub fn take_errors( &mut self ) -> Vec<Error> {
+    std::mem::take( &mut self.errors )
+  }
   fn restart( &mut self ) {
     self.code.clear();
+    self.lines.clear();
     self.constants.clear();
+    self.functions.clear();
+    self.handlers.clear();
     self.had_error = false;
   }
-  fn add_constant( &mut self, constant: Value ) -> u8 {
-    self.constants.push( constant );
-    ( self.constants.len() - 1 ) as u8
+
+  /// The index of `constant` in the pool, sharing an equal one already there.
+  fn add_constant( &mut self, constant: Value, token: &Token ) -> Result<u8, Error> {
+    let existing = self.constants.iter().position( | c | match ( c, &constant ) {
+      ( Value::Nil, Value::Nil ) => true,
+      ( Value::Boolean( a ), Value::Boolean( b ) ) => a == b,
+      ( Value::Number( a ), Value::Number( b ) ) => a.to_bits() == b.to_bits(),
+      ( Value::String( a ), Value::String( b ) ) => a == b,
+      _ => false
+    } );
+    let idx = match existing {
+      Some( idx ) => idx,
+      None => {
+        self.constants.push( constant );
+        self.constants.len() - 1
+      }
+    };
+    if idx > u8::MAX as usize {
+      return Err( Error::from_token( token, "Too many constants in one chunk.".into() ) );
+    }
+    Ok( idx as u8 )
   }
   fn emit_op( &mut self, op: Op ) -> usize {
     match op {
@@ -229,10 +1209,29 @@ impl Compiler {
       Op::Mul => { self.stack_size -= 1 },
       Op::Div => { self.stack_size -= 1 },
       Op::Neg => {},
+      Op::Not => {},
+      Op::Equal => { self.stack_size -= 1 },
+      Op::Less => { self.stack_size -= 1 },
+      Op::Greater => { self.stack_size -= 1 },
       Op::Pop => { self.stack_size -= 1 },
       Op::PushConstant( _ ) => { self.stack_size += 1 },
       Op::Dup( _ ) => { self.stack_size += 1 },
+      Op::SetLocal( _ ) => {},
       Op::Print => {},
+      Op::GetGlobal( _ ) => { self.stack_size += 1 },
+      Op::SetGlobal( _ ) => {},
+      Op::GetUpvalue( _ ) => { self.stack_size += 1 },
+      Op::SetUpvalue( _ ) => {},
+      Op::CloseUpvalue => { self.stack_size -= 1 },
+      Op::Closure( _ ) => { self.stack_size += 1 },
+      Op::Throw => { self.stack_size -= 1 },
+      Op::Rethrow => { self.stack_size -= 1 },
+      Op::Call( arg_count ) => { self.stack_size -= arg_count as usize },
+      Op::BuildList( count ) => { self.stack_size = self.stack_size + 1 - count as usize },
+      Op::BuildMap( count ) => { self.stack_size = self.stack_size + 1 - 2 * count as usize },
+      Op::GetIndex => { self.stack_size -= 1 },
+      Op::SetIndex => { self.stack_size -= 2 },
+      Op::GetProperty( _ ) => {},
       Op::And => { self.stack_size -= 1 },
       Op::Or => { self.stack_size -= 1 },
       Op::Return => {},
@@ -241,6 +1240,7 @@ impl Compiler {
       Op::Jump( _ ) => {},
     }
     self.code.push( op );
+    self.lines.push( self.line );
     self.code.len() - 1
   }
   fn compile_expr( &mut self, expr: &expr::Expr ) -> Result<(), Error> {
@@ -263,32 +1263,191 @@ impl Compiler {
     self.emit_op( Op::Return );
     // self.debug_print();
     // exit( 0 );
-    ( ByteCode::new( self.code.clone(), self.constants.clone() ), self.had_error )
+    ( ByteCode::new( self.code.clone(), self.lines.clone(), self.constants.clone(), self.functions.clone(), self.handlers.clone(),
+      self.locals.iter().map( | local | ( local.name.clone(), local.defined ) ).collect() ), self.had_error )
   }
-  fn debug_print( &self ) {
-    println!( "Code:" );
-    for op in &self.code {
-      println!( "{:?}", op );
-    }
-    println!( "Constants:" );
-    for ( idx, constant ) in self.constants.iter().enumerate() {
-      println!( "[{}] {:?}", idx, constant );
+  pub fn compile_expression( &mut self, expr: &expr::Expr ) -> ( ByteCode, bool ) {
+    if let Err( e ) = self.compile_expr( expr ) {
+      self.emit_error( &e );
     }
+    self.emit_op( Op::Return );
+    ( ByteCode::new( self.code.clone(), self.lines.clone(), self.constants.clone(), self.functions.clone(), self.handlers.clone(),
+      self.locals.iter().map( | local | ( local.name.clone(), local.defined ) ).collect() ), self.had_error )
   }
   fn emit_error( &mut self, error: &Error ) {
-    eprintln!( "[line {}] Error{}: {}", error.line, error.loc, error.msg );
+    self.errors.push( error.clone() );
     self.had_error = true;
   }
+
+  /// Attributes the ops emitted from here on to `token`'s line.
+  fn at( &mut self, token: &Token ) {
+    self.line = token.line;
+  }
+
+  /// How far below the top of the stack the local `name` lives, if it's a local.
+  fn local_depth( &self, name: &str ) -> Option<u8> {
+    for ( slot, local ) in self.locals.iter().enumerate().rev() {
+      if local.name == name {
+        return Some( ( self.stack_size - slot - 1 ) as u8 );
+      }
+    }
+    None
+  }
+
+  /// Finds `name` among the locals of the enclosing functions, threading it
+  /// through each function in between as an upvalue. Returns its upvalue index here.
+  fn resolve_upvalue( &mut self, name: &str ) -> Option<u8> {
+    let innermost = self.enclosing.len();
+    for level in ( 0..innermost ).rev() {
+      let scope = &mut self.enclosing[ level ];
+      if let Some( slot ) = scope.locals.iter().rposition( | local | local.name == name ) {
+        scope.locals[ slot ].captured = true;
+        let mut capture = Capture { is_local: true, index: slot as u8 };
+        for inner in level + 1..innermost {
+          capture = Capture { is_local: false, index: Compiler::add_capture( &mut self.enclosing[ inner ].upvalues, capture ) };
+        }
+        return Some( Compiler::add_capture( &mut self.upvalues, capture ) );
+      }
+    }
+    None
+  }
+  fn add_capture( upvalues: &mut Vec<Capture>, capture: Capture ) -> u8 {
+    match upvalues.iter().position( | c | *c == capture ) {
+      Some( idx ) => idx as u8,
+      None => {
+        upvalues.push( capture );
+        ( upvalues.len() - 1 ) as u8
+      }
+    }
+  }
+
+  /// Pops the local on top of the stack, closing its upvalue if a closure captured it.
+  fn emit_pop_local( &mut self, slot: usize ) {
+    if self.locals[ slot ].captured {
+      self.emit_op( Op::CloseUpvalue );
+    } else {
+      self.emit_op( Op::Pop );
+    }
+  }
+
+  /// Compiles `function`'s body out of line and emits the op creating its closure.
+  fn compile_function( &mut self, function: &stmt::Function ) -> Result<(), Error> {
+
+    // jump over the body
+    let __jump_over__ = self.emit_op( Op::Nop );
+    let entry = self.code.len();
+
+    // the body has its own locals: the callee in slot 0, then the parameters
+    self.enclosing.push( FunctionScope {
+      entry: self.entry.replace( entry ),
+      locals: std::mem::take( &mut self.locals ),
+      upvalues: std::mem::take( &mut self.upvalues ),
+      stack_size: std::mem::replace( &mut self.stack_size, 1 + function.params.len() ),
+      loops: std::mem::take( &mut self.loops ),
+      finallies: std::mem::take( &mut self.finallies )
+    } );
+    self.locals.push( Local::new( "" ) );
+    for param in &function.params {
+      self.locals.push( Local::new( &param.lexeme ) );
+    }
+
+    // [ ... byte-code for the body ... ], then return nil if it didn't already
+    let result = self.compile_body( &function.body, &function.name );
+
+    // back to the enclosing function
+    let captures = std::mem::take( &mut self.upvalues );
+    let scope = self.enclosing.pop().unwrap();
+    self.entry = scope.entry;
+    self.locals = scope.locals;
+    self.upvalues = scope.upvalues;
+    self.stack_size = scope.stack_size;
+    self.loops = scope.loops;
+    self.finallies = scope.finallies;
+    result?;
+
+    // #after#
+    let __after__ = self.code.len();
+    self.patch_jump( __jump_over__, __after__ );
+
+    if self.functions.len() > u8::MAX as usize {
+      return Err( Error::from_token( &function.name, "Too many functions in one script.".into() ) );
+    }
+    self.functions.push( Rc::new( FunctionProto {
+      name: function.name.lexeme.clone(),
+      arity: function.params.len(),
+      entry,
+      captures
+    } ) );
+    self.emit_op( Op::Closure( ( self.functions.len() - 1 ) as u8 ) );
+    Ok( () )
+  }
+  fn compile_body( &mut self, body: &Vec<Stmt>, name: &Token ) -> Result<(), Error> {
+    for stmt in body {
+      self.compile_stmt( stmt )?;
+    }
+    let nil = self.add_constant( Value::Nil, name )?;
+    self.emit_op( Op::PushConstant( nil ) );
+    self.emit_op( Op::Return );
+    Ok( () )
+  }
+
+  /// Runs the active finally blocks from `from` on, innermost first, ahead of a jump out of them.
+  fn emit_finallies( &mut self, from: usize ) -> Result<(), Error> {
+    let finallies = self.finallies.clone();
+    for idx in ( from..finallies.len() ).rev() {
+
+      // each is compiled as if outside its own try, so its exits don't run it again
+      self.finallies.truncate( idx );
+      let result = stmt::Visitor::visit_block_stmt( self, &finallies[ idx ] );
+      if result.is_err() {
+        self.finallies = finallies;
+        return result;
+      }
+    }
+    self.finallies = finallies;
+    Ok( () )
+  }
+
+  /// Pops the current loop's locals and emits a placeholder jump, returning its offset.
+  fn emit_loop_exit( &mut self, keyword: &Token ) -> Result<usize, Error> {
+    let ( loop_stack_size, finally_depth ) = match self.loops.last() {
+      Some( loop_ ) => ( loop_.stack_size, loop_.finally_depth ),
+      None => return Err( Error::from_token( keyword, "Internal error: No loop to jump out of.".into() ) )
+    };
+    self.emit_finallies( finally_depth )?;
+
+    // the locals stay declared for the code that follows in the same block
+    let stack_size = self.stack_size;
+    for slot in ( loop_stack_size..stack_size ).rev() {
+      self.emit_pop_local( slot );
+    }
+    self.stack_size = stack_size;
+    Ok( self.emit_op( Op::Nop ) )
+  }
+  fn patch_jump( &mut self, from: usize, to: usize ) {
+    *self.code.get_mut( from ).unwrap() = Op::Jump( to as i16 - from as i16 );
+  }
 }
 
 impl expr::Visitor<Result<(), Error>> for Compiler {
   fn visit_assign_expr( &mut self, assign: &expr::Assign ) -> Result<(), Error> {
-    todo!()
+    self.compile_expr( &assign.rhs )?;
+    self.at( &assign.lhs.name );
+    if let Some( depth ) = self.local_depth( &assign.lhs.name.lexeme ) {
+      self.emit_op( Op::SetLocal( depth ) );
+    } else if let Some( idx ) = self.resolve_upvalue( &assign.lhs.name.lexeme ) {
+      self.emit_op( Op::SetUpvalue( idx ) );
+    } else {
+      let idx = self.add_constant( Value::String( assign.lhs.name.lexeme.clone() ), &assign.lhs.name )?;
+      self.emit_op( Op::SetGlobal( idx ) );
+    }
+    Ok( () )
   }
 
   fn visit_binary_expr( &mut self, binary: &expr::Binary ) -> Result<(), Error> {
     self.compile_expr( &binary.left )?;
     self.compile_expr( &binary.right )?;
+    self.at( &binary.operator );
     match binary.operator.token_type {
       TokenType::Minus => { self.emit_op( Op::Sub ); },
       TokenType::Plus  => { self.emit_op( Op::Add ); },
@@ -296,53 +1455,128 @@ impl expr::Visitor<Result<(), Error>> for Compiler {
       TokenType::Star  => { self.emit_op( Op::Mul ); },
       TokenType::And   => { self.emit_op( Op::And ); },
       TokenType::Or    => { self.emit_op( Op::Or  ); },
-      
-      _ => {}
+
+      // comparisons [ the negated ones are built from their opposites ]
+      TokenType::EqualEqual   => { self.emit_op( Op::Equal ); },
+      TokenType::BangEqual    => { self.emit_op( Op::Equal ); self.emit_op( Op::Not ); },
+      TokenType::Less         => { self.emit_op( Op::Less ); },
+      TokenType::GreaterEqual => { self.emit_op( Op::Less ); self.emit_op( Op::Not ); },
+      TokenType::Greater      => { self.emit_op( Op::Greater ); },
+      TokenType::LessEqual    => { self.emit_op( Op::Greater ); self.emit_op( Op::Not ); },
+
+      _ => return Err( Error::from_token( &binary.operator,
+        "Internal error: compilation of this binary operator is not implemented.".into() ) )
     }
     Ok( () )
   }
 
   fn visit_call_expr( &mut self, call: &expr::Call ) -> Result<(), Error> {
-    todo!()
+    self.compile_expr( &call.callee )?;
+    for arg in &call.arguments {
+      self.compile_expr( arg )?;
+    }
+    self.at( &call.paren );
+    self.emit_op( Op::Call( call.arguments.len() as u8 ) );
+    Ok( () )
+  }
+
+  fn visit_get_expr( &mut self, get: &expr::Get ) -> Result<(), Error> {
+    self.compile_expr( &get.object )?;
+    self.at( &get.name );
+    let idx = self.add_constant( Value::String( get.name.lexeme.clone() ), &get.name )?;
+    self.emit_op( Op::GetProperty( idx ) );
+    Ok( () )
   }
 
   fn visit_grouping_expr( &mut self, grouping: &expr::Grouping ) -> Result<(), Error> {
     self.compile_expr( &grouping.expression )
   }
 
+  fn visit_index_expr( &mut self, index: &expr::Index ) -> Result<(), Error> {
+    self.compile_expr( &index.object )?;
+    self.compile_expr( &index.index )?;
+    self.at( &index.bracket );
+    self.emit_op( Op::GetIndex );
+    Ok( () )
+  }
+
+  fn visit_lambda_expr( &mut self, lambda: &expr::Lambda ) -> Result<(), Error> {
+    self.at( &lambda.keyword );
+    self.compile_function( &lambda.function )
+  }
+
+  fn visit_list_expr( &mut self, list: &expr::List ) -> Result<(), Error> {
+    if list.elements.len() > u8::MAX as usize {
+      return Err( Error::from_token( &list.bracket, "Too many elements in a list literal.".into() ) );
+    }
+    for element in &list.elements {
+      self.compile_expr( element )?;
+    }
+    self.at( &list.bracket );
+    self.emit_op( Op::BuildList( list.elements.len() as u8 ) );
+    Ok( () )
+  }
+
   fn visit_literal_expr( &mut self, literal: &expr::Literal ) -> Result<(), Error> {
-    if let TokenType::Number = literal.value.token_type {
-      let idx = self.add_constant( Value::Number( literal.value.lexeme.parse::<f64>().unwrap() ) );
-      self.emit_op( Op::PushConstant( idx ) );
-      Ok( () )
-    } else {
-      Err( Error::from_token( &literal.value, "Only doing numbers presently.".into() ) )
+    let constant = match literal.value.token_type {
+      TokenType::Number => Value::Number( literal.value.lexeme.parse::<f64>().unwrap() ),
+      TokenType::String => Value::String( literal.value.lexeme.clone() ),
+      TokenType::True => Value::Boolean( true ),
+      TokenType::False => Value::Boolean( false ),
+      TokenType::Nil => Value::Nil,
+      _ => unreachable!( "Internal error: No other token types can be converted to Value." )
+    };
+    let idx = self.add_constant( constant, &literal.value )?;
+    self.at( &literal.value );
+    self.emit_op( Op::PushConstant( idx ) );
+    Ok( () )
+  }
+
+  fn visit_map_expr( &mut self, map: &expr::Map ) -> Result<(), Error> {
+    if map.entries.len() > u8::MAX as usize {
+      return Err( Error::from_token( &map.brace, "Too many entries in a map literal.".into() ) );
     }
+    for ( key, value ) in &map.entries {
+      self.compile_expr( key )?;
+      self.compile_expr( value )?;
+    }
+    self.at( &map.brace );
+    self.emit_op( Op::BuildMap( map.entries.len() as u8 ) );
+    Ok( () )
+  }
+
+  fn visit_set_index_expr( &mut self, set_index: &expr::SetIndex ) -> Result<(), Error> {
+    self.compile_expr( &set_index.object )?;
+    self.compile_expr( &set_index.index )?;
+    self.compile_expr( &set_index.value )?;
+    self.at( &set_index.bracket );
+    self.emit_op( Op::SetIndex );
+    Ok( () )
   }
 
   fn visit_unary_expr( &mut self, unary: &expr::Unary ) -> Result<(), Error> {
     self.compile_expr( &unary.right )?;
-    if let TokenType::Minus = unary.operator.token_type {
-      self.emit_op( Op::Neg );
-      Ok( () )
-    }
-    else {
-      Err( Error::from_token( &unary.operator,
-        "Only doing unary minus on constants presently".into() ) )
+    self.at( &unary.operator );
+    match unary.operator.token_type {
+      TokenType::Minus => { self.emit_op( Op::Neg ); },
+      TokenType::Bang => { self.emit_op( Op::Not ); },
+      _ => return Err( Error::from_token( &unary.operator,
+        "Internal error: compilation of this unary operator is not implemented.".into() ) )
     }
+    Ok( () )
   }
 
   fn visit_variable_expr( &mut self, variable: &expr::Variable ) -> Result<(), Error> {
-    let mut peek_depth: Option<u8> = None;
-    for ( slot, name ) in self.locals.iter().enumerate().rev() {
-      if *name == variable.name.lexeme {
-        println!( "\nname = '{}', stack_size = {} slot = {}", name, self.stack_size, slot );
-        peek_depth = Some( ( self.stack_size - slot - 1 ) as u8 );
-        break;
-      }
+    self.at( &variable.name );
+    if let Some( depth ) = self.local_depth( &variable.name.lexeme ) {
+      self.emit_op( Op::Dup( depth ) );
+    } else if let Some( idx ) = self.resolve_upvalue( &variable.name.lexeme ) {
+      self.emit_op( Op::GetUpvalue( idx ) );
+    } else {
+      // not a local, so leave it to the VM's globals
+      let idx = self.add_constant( Value::String( variable.name.lexeme.clone() ), &variable.name )?;
+      self.emit_op( Op::GetGlobal( idx ) );
     }
-    assert( peek_depth.is_some(), format!( "Could not find local variable '{}'", variable.name.lexeme ) );
-    self.emit_op( Op::Dup( peek_depth.unwrap() ) );
     Ok( () )
   }
 }
@@ -360,20 +1594,43 @@ impl stmt::Visitor<Result<(), Error>> for Compiler {
 
     // delete variables local to the block just finished
     while self.stack_size > stack_size {
-      self.emit_op( Op::Pop );
+      self.emit_pop_local( self.locals.len() - 1 );
       self.locals.pop();
     }
     Ok( () )
   }
 
+  fn visit_break_stmt( &mut self, break_: &stmt::Break ) -> Result<(), Error> {
+    let __break__ = self.emit_loop_exit( &break_.keyword )?;
+    self.loops.last_mut().unwrap().breaks.push( __break__ );
+    Ok( () )
+  }
+
+  fn visit_continue_stmt( &mut self, continue_: &stmt::Continue ) -> Result<(), Error> {
+    let __continue__ = self.emit_loop_exit( &continue_.keyword )?;
+    self.loops.last_mut().unwrap().continues.push( __continue__ );
+    Ok( () )
+  }
+
   fn visit_expression_stmt( &mut self, expression: &stmt::Expression ) -> Result<(), Error> {
     self.compile_expr( &expression.expression )?;
     self.emit_op( Op::Pop );
     Ok( () )
   }
 
+  fn visit_for_in_stmt( &mut self, for_in: &stmt::ForIn ) -> Result<(), Error> {
+    Err( Error::from_token( &for_in.keyword, "For-in loops only run on the tree-walk interpreter; use a while loop under --vm.".into() ) )
+  }
+
   fn visit_function_stmt( &mut self, function: &stmt::Function ) -> Result<(), Error> {
-    todo!()
+
+    // declared first, so the body can capture it to recurse
+    self.at( &function.name );
+    self.locals.push( Local::new( &function.name.lexeme ) );
+    let slot = self.locals.len() - 1;
+    self.compile_function( function )?;
+    self.locals[ slot ].defined = self.code.len();
+    Ok( () )
   }
 
   fn visit_if_stmt( &mut self, if_: &stmt::If ) -> Result<(), Error> {
@@ -424,7 +1681,95 @@ impl stmt::Visitor<Result<(), Error>> for Compiler {
   }
 
   fn visit_return_stmt( &mut self, return_: &stmt::Return ) -> Result<(), Error> {
-    todo!()
+    if let Some( expr ) = &return_.value {
+      self.compile_expr( expr )?;
+    } else {
+      let nil = self.add_constant( Value::Nil, &return_.keyword )?;
+      self.emit_op( Op::PushConstant( nil ) );
+    }
+
+    // the VM discards the whole frame, so the value is all there is to account for
+    self.emit_finallies( 0 )?;
+    self.at( &return_.keyword );
+    self.emit_op( Op::Return );
+    self.stack_size -= 1;
+    Ok( () )
+  }
+
+  fn visit_throw_stmt( &mut self, throw: &stmt::Throw ) -> Result<(), Error> {
+    self.compile_expr( &throw.value )?;
+    self.at( &throw.keyword );
+    self.emit_op( Op::Throw );
+    Ok( () )
+  }
+
+  fn visit_try_stmt( &mut self, try_: &stmt::Try ) -> Result<(), Error> {
+
+    // save stack size
+    let stack_size = self.stack_size;
+
+    // 'break', 'continue' and 'return' run the finally block on their way out
+    if let Some( finally ) = &try_.finally {
+      self.finallies.push( finally.clone() );
+    }
+    let finally_depth = self.finallies.len();
+
+    // #try#
+    // [ ... byte-code for the body ... ], [ ... the finally block ... ], jump to #done#
+    let __try__ = self.code.len();
+    self.visit_block_stmt( &try_.body )?;
+    let __try_end__ = self.code.len();
+    if try_.finally.is_some() {
+      self.emit_finallies( finally_depth - 1 )?;
+    }
+    let mut jumps_to_done = vec![ self.emit_op( Op::Nop ) ];
+
+    // code whose exceptions must still run the finally block
+    let mut protected = ( __try__, __try_end__ );
+
+    // #catch#  [ the handler pushes the exception, which becomes the caught local ]
+    // [ ... byte-code for the catch block ... ], pop, [ ... the finally block ... ], jump to #done#
+    if let Some( catch ) = &try_.catch {
+      let __catch__ = self.code.len();
+      self.handlers.push( Handler { owner: self.entry, start: __try__, end: __try_end__, target: __catch__, stack_size, finally: false } );
+      self.stack_size = stack_size + 1;
+      self.locals.push( Local::new( &catch.name.lexeme ) );
+      self.visit_block_stmt( &catch.body )?;
+      self.emit_pop_local( self.locals.len() - 1 );
+      self.locals.pop();
+      protected = ( __catch__, self.code.len() );
+      if try_.finally.is_some() {
+        self.emit_finallies( finally_depth - 1 )?;
+      }
+      jumps_to_done.push( self.emit_op( Op::Nop ) );
+    }
+
+    // #finally#  [ for an exception escaping the try or catch block ]
+    // [ ... byte-code for the finally block ... ], rethrow
+    if let Some( finally ) = &try_.finally {
+      self.finallies.pop();
+      let __finally__ = self.code.len();
+      self.handlers.push( Handler {
+        owner: self.entry,
+        start: protected.0,
+        end: protected.1,
+        target: __finally__,
+        stack_size,
+        finally: true
+      } );
+      self.stack_size = stack_size + 1;
+      self.locals.push( Local::new( "" ) );
+      self.visit_block_stmt( finally )?;
+      self.locals.pop();
+      self.emit_op( Op::Rethrow );
+    }
+
+    // #done#
+    let __done__ = self.code.len();
+    for __jump__ in jumps_to_done {
+      self.patch_jump( __jump__, __done__ );
+    }
+    Ok( () )
   }
 
   fn visit_var_stmt( &mut self, var: &stmt::Var ) -> Result<(), Error> {
@@ -433,11 +1778,80 @@ impl stmt::Visitor<Result<(), Error>> for Compiler {
     } else {
       self.emit_op( Op::PushConstant( 0 ) );
     }
-    self.locals.push( var.name.lexeme.clone() );
+    self.locals.push( Local { defined: self.code.len(), ..Local::new( &var.name.lexeme ) } );
     Ok( () )
   }
 
   fn visit_while_stmt( &mut self, while_: &stmt::While ) -> Result<(), Error> {
-    todo!()
+
+    // save stack size
+    let stack_size = self.stack_size;
+
+    // #start#
+    // [ ... byte-code for the condition ... ]
+    let __start__ = self.code.len();
+    self.compile_expr( &while_.condition )?;
+
+    // jump if false to #exit#
+    let __jump_if_false__ = self.emit_op( Op::Nop );
+
+    // pop, [ ... byte-code for the body ... ]
+    self.emit_op( Op::Pop );
+    self.loops.push( Loop {
+      breaks: Vec::new(),
+      continues: Vec::new(),
+      stack_size,
+      finally_depth: self.finallies.len()
+    } );
+    self.compile_stmt( &while_.body )?;
+    let loop_ = self.loops.pop().unwrap();
+
+    // #continue#
+    // [ ... byte-code for the increment ... ], pop
+    let __continue__ = self.code.len();
+    if let Some( increment ) = &while_.increment {
+      self.compile_expr( increment )?;
+      self.emit_op( Op::Pop );
+    }
+
+    // jump to #start#
+    let __jump_to_start__ = self.emit_op( Op::Nop );
+
+    // #exit#
+    // pop [ the condition, which is still on the stack ]
+    self.stack_size = stack_size + 1;
+    let __exit__ = self.emit_op( Op::Pop );
+
+    // #done#
+    let __done__ = self.code.len();
+
+    // stitch up the jumps
+    *self.code.get_mut( __jump_if_false__ ).unwrap()
+      = Op::JumpIfFalse( ( __exit__ - __jump_if_false__ ) as i16 );
+    self.patch_jump( __jump_to_start__, __start__ );
+    for __break__ in loop_.breaks {
+      self.patch_jump( __break__, __done__ );
+    }
+    for __continue_jump__ in loop_.continues {
+      self.patch_jump( __continue_jump__, __continue__ );
+    }
+    Ok( () )
   }
-}
\ No newline at end of file
+}
+
+/// Counts a String `value` where it's found, and queues anything that holds further values.
+fn reach( value: &Value, bytes: &mut usize, values: &mut Vec<Value> ) {
+  match value {
+    Value::String( s ) => *bytes += s.len(),
+    Value::List( _ ) | Value::Map( _ ) | Value::Closure( _ ) | Value::Method( _, _ ) => values.push( value.clone() ),
+    _ => {}
+  }
+}
+
+/// The value a runtime error is caught as: a Map of its `message` and `line`.
+fn error_value( error: &Error ) -> Value {
+  let mut map: BTreeMap<MapKey, Value> = BTreeMap::new();
+  map.insert( MapKey::String( "message".into() ), Value::String( error.msg.clone() ) );
+  map.insert( MapKey::String( "line".into() ), Value::Number( error.line as f64 ) );
+  Value::Map( RcMut::new( map ) )
+}
@@ -0,0 +1,193 @@
+//////////////////////////////
+// private module rlox::cst //
+//////////////////////////////
+
+
+/////////
+// use //
+/////////
+
+use std::fmt::{self, Display};
+
+use crate::token::TokenType;
+
+
+//////////////////
+// declarations //
+//////////////////
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TriviaKind {
+  Whitespace,
+  Comment
+}
+
+/// Source text that isn't part of any token.
+#[derive(Clone, PartialEq)]
+pub struct Trivia {
+  pub kind: TriviaKind,
+  pub text: String
+}
+
+/// A token exactly as written, with the trivia that comes before it.
+///
+/// The end of the file is a token too, with empty text, so trailing trivia has a home.
+#[derive(Clone, PartialEq)]
+pub struct SyntaxToken {
+  pub token_type: TokenType,
+  pub leading: Vec<Trivia>,
+
+  // quotes and all, for strings
+  pub text: String,
+  pub line: u32
+}
+
+/// What a node of the concrete syntax tree stands for, one per production of the grammar.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NodeKind {
+  Script,
+
+  // declarations and statements
+  FunDecl, Params, VarDecl, Block, PrintStmt, IfStmt, WhileStmt, ForStmt, ForInStmt, ExprStmt,
+  ReturnStmt, BreakStmt, ContinueStmt, ThrowStmt, TryStmt, CatchClause, FinallyClause,
+
+  // expressions [ Assign covers assignment to an index too ]
+  Assign, Binary, Unary, Call, Get, Index, Grouping, Lambda, ArrowLambda, List, Map, MapEntry,
+  Literal, Variable
+}
+
+#[derive(Clone)]
+pub enum SyntaxElement {
+  Node( SyntaxNode ),
+  Token( SyntaxToken )
+}
+
+/// A node of the concrete syntax tree: every token beneath it, in order, along
+/// with their trivia, so printing the tree gives back the source byte-for-byte.
+#[derive(Clone)]
+pub struct SyntaxNode {
+  pub kind: NodeKind,
+  pub children: Vec<SyntaxElement>
+}
+
+/// What the parser did, as it did it, for building the concrete syntax tree afterwards.
+#[derive(Clone, Copy)]
+pub enum Event {
+  Start( NodeKind ),
+
+  // consumed the next token
+  Token,
+  Finish
+}
+
+
+/////////////////////
+// implementations //
+/////////////////////
+
+impl SyntaxNode {
+  fn new( kind: NodeKind ) -> SyntaxNode {
+    SyntaxNode {
+      kind,
+      children: Vec::new()
+    }
+  }
+
+  /// The node's child nodes, skipping its tokens.
+  pub fn nodes( &self ) -> impl Iterator<Item = &SyntaxNode> {
+    self.children.iter().filter_map( | child | match child {
+      SyntaxElement::Node( node ) => Some( node ),
+      SyntaxElement::Token( _ ) => None
+    } )
+  }
+
+  /// The node's tokens, depth first.
+  pub fn tokens( &self ) -> Vec<&SyntaxToken> {
+    let mut tokens = Vec::new();
+    for child in &self.children {
+      match child {
+        SyntaxElement::Node( node ) => tokens.extend( node.tokens() ),
+        SyntaxElement::Token( token ) => tokens.push( token )
+      }
+    }
+    tokens
+  }
+}
+
+impl Display for Trivia {
+  fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+    write!( f, "{}", self.text )
+  }
+}
+
+impl Display for SyntaxToken {
+  fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+    for trivia in &self.leading {
+      write!( f, "{}", trivia )?;
+    }
+    write!( f, "{}", self.text )
+  }
+}
+
+impl Display for SyntaxElement {
+  fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+    match self {
+      SyntaxElement::Node( node ) => write!( f, "{}", node ),
+      SyntaxElement::Token( token ) => write!( f, "{}", token )
+    }
+  }
+}
+
+impl Display for SyntaxNode {
+  fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+    for child in &self.children {
+      write!( f, "{}", child )?;
+    }
+    Ok( () )
+  }
+}
+
+/// Assembles the parser's events over the scanner's tokens into a tree rooted at a script.
+pub fn build( events: &[Event], tokens: Vec<SyntaxToken> ) -> SyntaxNode {
+  let mut tokens = tokens.into_iter();
+  let mut stack = vec![ SyntaxNode::new( NodeKind::Script ) ];
+  for event in events {
+    match event {
+      Event::Start( kind ) => stack.push( SyntaxNode::new( *kind ) ),
+      Event::Token => if let Some( token ) = tokens.next() {
+        stack.last_mut().unwrap().children.push( SyntaxElement::Token( token ) );
+      },
+      Event::Finish => close( &mut stack )
+    }
+  }
+
+  // nodes a parse error left open, then the end of file the parser stopped at
+  while stack.len() > 1 {
+    close( &mut stack );
+  }
+  let mut root = stack.pop().unwrap();
+  root.children.extend( tokens.map( SyntaxElement::Token ) );
+  root
+}
+
+fn close( stack: &mut Vec<SyntaxNode> ) {
+  if stack.len() > 1 {
+    let node = stack.pop().unwrap();
+    stack.last_mut().unwrap().children.push( SyntaxElement::Node( node ) );
+  }
+}
+
+/// Splits the text between two tokens into runs of whitespace and `//` comments.
+pub fn split_trivia( mut text: &str ) -> Vec<Trivia> {
+  let mut trivia = Vec::new();
+  while !text.is_empty() {
+    let ( kind, len ) = if text.starts_with( "//" ) {
+      ( TriviaKind::Comment, text.find( '\n' ).unwrap_or( text.len() ) )
+    } else {
+      ( TriviaKind::Whitespace, text.find( "//" ).unwrap_or( text.len() ) )
+    };
+    trivia.push( Trivia { kind, text: text[ ..len ].into() } );
+    text = &text[ len.. ];
+  }
+  trivia
+}
@@ -0,0 +1,158 @@
+////////////////////////////////
+// public module rlox::engine //
+////////////////////////////////
+
+
+/////////
+// use //
+/////////
+
+use std::{io::Write, rc::Rc, sync::{atomic::AtomicBool, Arc}};
+
+use crate::{error::{Diagnostics, Error}, eval::Eval, interpreter::{Interpreter, ENGINE_MAX_DEPTH}, native::{Arity, Native, NativeFn},
+  parser::Parser, resolver::Resolver, scanner::Scanner, token::{Token, TokenType}};
+
+
+//////////////////
+// declarations //
+//////////////////
+
+/// A self-contained Lox session for host applications.
+///
+/// Globals persist between calls to `eval`, so a host can load a script once and
+/// then call into it. Engines share no state, so several can run side by side.
+pub struct Engine {
+  interpreter: Interpreter,
+  resolver: Resolver
+}
+
+
+/////////////////////
+// implementations //
+/////////////////////
+
+impl Engine {
+  pub fn new() -> Engine {
+
+    // sized for the host's stack rather than the one `rlox::with_stack` gives
+    let mut interpreter = Interpreter::new();
+    interpreter.set_max_depth( ENGINE_MAX_DEPTH );
+    Engine {
+      interpreter,
+      resolver: Resolver::new()
+    }
+  }
+
+  /// Runs `src` against this engine's globals, returning the value of the last statement.
+  pub fn eval( &mut self, src: &str ) -> Result<Eval, Diagnostics> {
+
+    // scanner / lexer
+    let mut scanner = Scanner::new();
+    let ( tokens, had_scan_error ) = scanner.scan( src.into() );
+    if had_scan_error {
+      return Err( Diagnostics::new( scanner.take_errors() ) );
+    }
+
+    // parser
+    let mut parser = Parser::new();
+    let ( mut stmts, had_parse_error ) = parser.parse( tokens );
+    if had_parse_error {
+      return Err( Diagnostics::new( parser.take_errors() ) );
+    }
+
+    // resolver [ against the globals bound so far ]
+    let had_resolve_error = self.resolver.resolve_with_globals( &mut stmts, &self.global_names() );
+    if had_resolve_error {
+      return Err( Diagnostics::new( self.resolver.take_errors() ) );
+    }
+
+    // interpreter
+    let ( eval, had_error ) = self.interpreter.interpret( &stmts );
+    if had_error {
+      return Err( Diagnostics::new( self.interpreter.take_errors() ) );
+    }
+    Ok( eval )
+  }
+
+  /// Calls the global function `name` with `args`.
+  pub fn call_function( &mut self, name: &str, args: &[Eval] ) -> Result<Eval, Diagnostics> {
+    let token = Token { token_type: TokenType::Identifier, lexeme: name.into(), line: 0 };
+    let callee = match self.interpreter.global( name ) {
+      Some( callee ) => callee,
+      None => return Err( Diagnostics::new( vec![
+        Error::from_token( &token, "Undeclared symbol.".into() ) ] ) )
+    };
+    let ( eval, had_error ) = self.interpreter.call_function( callee, args.to_vec(), &token );
+    if had_error {
+      return Err( Diagnostics::new( self.interpreter.take_errors() ) );
+    }
+    Ok( eval )
+  }
+
+  /// Limits how deeply calls may nest before a script fails with a stack overflow.
+  ///
+  /// The default, `ENGINE_MAX_DEPTH`, is safe on any thread with `HOST_STACK_SIZE` of stack.
+  /// Deeper limits need a bigger stack: under `rlox::with_stack` there's room for `MAX_DEPTH`.
+  pub fn set_max_depth( &mut self, max_depth: usize ) {
+    self.interpreter.set_max_depth( max_depth );
+  }
+
+  /// Limits each call to `eval` or `call_function` to `budget` statements [ None for no limit ].
+  pub fn set_budget( &mut self, budget: Option<u64> ) {
+    self.interpreter.set_budget( budget );
+  }
+
+  /// Caps the bytes each call to `eval` or `call_function` may hold [ None for the built-in ceiling ].
+  pub fn set_memory_limit( &mut self, limit: Option<usize> ) {
+    self.interpreter.set_memory_limit( limit );
+  }
+
+  /// Sends the output of print statements to `out` instead of stdout.
+  pub fn set_output( &mut self, out: Box<dyn Write> ) {
+    self.interpreter.set_output( out );
+  }
+
+  /// A flag that, once set from any thread, stops the running script at its next statement.
+  pub fn interrupt_handle( &self ) -> Arc<AtomicBool> {
+    self.interpreter.interrupt_handle()
+  }
+
+  /// The code the last call to `eval` or `call_function` passed to `exit()`, if the script asked to stop.
+  pub fn exit_code( &self ) -> Option<i32> {
+    self.interpreter.exit_code()
+  }
+  pub fn get_global( &self, name: &str ) -> Option<Eval> {
+    self.interpreter.global( name )
+  }
+
+  /// Binds `name` to `value`, replacing any existing global of that name.
+  pub fn set_global( &mut self, name: &str, value: Eval ) {
+    self.interpreter.define_global( name, value );
+  }
+
+  /// Exposes `native` to scripts as a global function under its own name.
+  pub fn register_native( &mut self, native: impl NativeFn + 'static ) {
+    let name = native.name().to_string();
+    self.interpreter.define_global( &name, Eval::Native( Rc::new( native ) ) );
+  }
+  pub fn register_fn( &mut self, name: &str, arity: Arity, fun: fn( &mut Interpreter, &[Eval] ) -> Result<Eval, Error> ) {
+    self.register_native( Native::new( name, arity, fun ) );
+  }
+  pub fn globals( &self ) -> Vec<( String, Eval )> {
+    self.interpreter.globals()
+  }
+
+  /// Discards every global, returning the engine to its freshly constructed state.
+  pub fn reset( &mut self ) {
+    self.interpreter.restart();
+  }
+  fn global_names( &self ) -> Vec<String> {
+    self.interpreter.globals().into_iter().map( | ( name, _ ) | name ).collect()
+  }
+}
+
+impl Default for Engine {
+  fn default() -> Engine {
+    Engine::new()
+  }
+}
@@ -2,7 +2,7 @@
 
 use std::collections::HashMap;
 
-use crate::{eval::Eval, token::Token, util::{assert, RcMut}};
+use crate::{eval::Eval, prelude::define_prelude, token::Token, util::{assert, RcMut}};
 
 #[derive(Clone)]
 pub struct Env {
@@ -13,13 +13,13 @@ pub struct Env {
 
 impl Env {
   pub fn create_global() -> RcMut<Env> {
-    RcMut::new(
-      Env {
-        enclosing: None,
-        values: HashMap::new(),
-        depth: 0
-      }
-    )
+    let mut global = Env {
+      enclosing: None,
+      values: HashMap::new(),
+      depth: 0
+    };
+    define_prelude( &mut global );
+    RcMut::new( global )
   }
   pub fn new_with_enclosing( enclosing: &RcMut<Env> ) -> RcMut<Env> {
     // println!( "Enclosing new scope at depth {}", enclosing.view().depth + 1 );
@@ -68,6 +68,28 @@ impl Env {
       format!( "Internal error: Key '{}' not found at depth {} for writing. Was the symbol created?", name.lexeme, self.depth ) );
     self.values.insert( name.lexeme.clone(), value.clone() );
   }
+  pub fn enclosing( &self ) -> Option<&RcMut<Env>> {
+    self.enclosing.as_ref()
+  }
+  pub fn bindings( &self ) -> impl Iterator<Item = ( &String, &Eval )> {
+    self.values.iter()
+  }
+  pub fn get_symbol( &self, name: &str ) -> Option<Eval> {
+    self.values.get( name ).cloned()
+  }
+  pub fn is_global( &self ) -> bool {
+    self.depth == 0
+  }
+  pub fn define_symbol( &mut self, name: &Token, value: &Eval ) {
+    self.values.insert( name.lexeme.clone(), value.clone() );
+  }
+  pub fn symbols( &self ) -> Vec<( String, Eval )> {
+    let mut symbols: Vec<( String, Eval )> = self.values.iter()
+      .map( | ( name, value ) | ( name.clone(), value.clone() ) )
+      .collect();
+    symbols.sort_by( | a, b | a.0.cmp( &b.0 ) );
+    symbols
+  }
   pub fn create_symbol( &mut self, name: &Token, value: &Eval ) {
     assert( !self.values.contains_key( &name.lexeme ), 
       format!( "Internal error: Creating symbol '{}' at depth {}, but it already exists.", name.lexeme, self.depth ) );
@@ -7,6 +7,8 @@
 // use //
 /////////
 
+use std::fmt::Display;
+
 use crate::token::Token;
 
 
@@ -15,11 +17,28 @@ use crate::token::Token;
 // declarations //
 //////////////////
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Error {
   pub line: u32,
   pub loc: String,
-  pub msg: String
+  pub msg: String,
+
+  // the calls a runtime error escaped from, outermost first [ empty for the rest ]
+  pub trace: Vec<TraceEntry>
+}
+
+/// A line of a stack trace: the function, and the line it had reached.
+#[derive(Debug, Clone)]
+pub struct TraceEntry {
+  pub function: String,
+  pub line: u32
+}
+
+/// Every error reported by one pass through the scanner, parser, resolver and
+/// interpreter, in the order they were found.
+#[derive(Debug, Clone)]
+pub struct Diagnostics {
+  pub errors: Vec<Error>
 }
 
 
@@ -35,11 +54,99 @@ impl Error {
   //     msg
   //   }
   // }
+  pub fn from_msg( msg: String ) -> Error {
+    Error {
+      line: 0,
+      loc: "".into(),
+      msg,
+      trace: Vec::new()
+    }
+  }
   pub fn from_token( t: &Token, msg: String ) -> Error {
     Error {
       line: t.line,
       loc: format!( " at '{}'", t.lexeme ),
-      msg
+      msg,
+      trace: Vec::new()
     }
   }
-}
\ No newline at end of file
+
+  /// Places an error that has no location of its own (see `from_msg`) at `t`.
+  pub fn at_call( error: Error, t: &Token ) -> Error {
+    if error.line == 0 && error.loc.is_empty() {
+      Error::from_token( t, error.msg )
+    } else {
+      error
+    }
+  }
+
+  /// Attaches a stack trace, given the function name and call-site line of each
+  /// call in progress, outermost first. An error keeps the first trace it gets.
+  pub fn with_trace( mut self, calls: &[( String, u32 )] ) -> Error {
+    if calls.is_empty() || !self.trace.is_empty() {
+      return self;
+    }
+    let mut function = "script".to_string();
+    for ( name, line ) in calls {
+      self.trace.push( TraceEntry { function, line: *line } );
+      function = format!( "{}()", name );
+    }
+    self.trace.push( TraceEntry { function, line: self.line } );
+    self
+  }
+}
+impl Display for Error {
+  fn fmt( &self, f: &mut std::fmt::Formatter<'_> ) -> std::fmt::Result {
+    write!( f, "[line {}] Error{}: {}", self.line, self.loc, self.msg )?;
+    if !self.trace.is_empty() {
+      write!( f, "\nStack trace (innermost last):" )?;
+
+      // runs of the same entry, as deep recursion leaves, are folded into one line
+      let mut idx = 0;
+      while idx < self.trace.len() {
+        let entry = &self.trace[ idx ];
+        write!( f, "\n  [line {}] in {}", entry.line, entry.function )?;
+        let repeats = self.trace[ idx + 1.. ].iter()
+          .take_while( | next | next.line == entry.line && next.function == entry.function ).count();
+        if repeats > 0 {
+          write!( f, "\n  ... repeated {} more times", repeats )?;
+        }
+        idx += repeats + 1;
+      }
+    }
+    Ok( () )
+  }
+}
+
+impl Diagnostics {
+  pub fn new( errors: Vec<Error> ) -> Diagnostics {
+    Diagnostics {
+      errors
+    }
+  }
+  pub fn report( &self ) {
+    for error in &self.errors {
+      eprintln!( "{}", error );
+    }
+  }
+}
+
+pub fn report_warnings( warnings: &Vec<Error> ) {
+  for warning in warnings {
+    eprintln!( "[line {}] Warning{}: {}", warning.line, warning.loc, warning.msg );
+  }
+}
+
+impl Display for Diagnostics {
+  fn fmt( &self, f: &mut std::fmt::Formatter<'_> ) -> std::fmt::Result {
+    for ( idx, error ) in self.errors.iter().enumerate() {
+      if idx > 0 {
+        writeln!( f )?;
+      }
+      write!( f, "{}", error )?;
+    }
+    Ok( () )
+  }
+}
+
+impl std::error::Error for Diagnostics {}
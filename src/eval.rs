@@ -1,6 +1,6 @@
-use std::fmt::Display;
+use std::{collections::{BTreeMap, HashMap}, fmt::{Debug, Display}, rc::Rc};
 
-use crate::{env::Env, stmt::Function, util::RcMut};
+use crate::{env::Env, maps::MapKey, native::NativeFn, stmt::Function, util::RcMut};
 
 #[derive(Clone)]
 pub enum Eval {
@@ -8,7 +8,37 @@ pub enum Eval {
   StringLiteral( String ),
   Bool( bool ),
   Nil,
-  Fun( Function, RcMut<Env> )
+  Fun( Function, RcMut<Env> ),
+  List( RcMut<Vec<Eval>> ),
+  Map( RcMut<BTreeMap<MapKey, Eval>> ),
+  Native( Rc<dyn NativeFn> ),
+  Method( Rc<Method> ),
+  Module( Rc<Module> ),
+  Iterator( Rc<Cursor> )
+}
+
+/// A native looked up on a value, as in `"abc".upper`.
+///
+/// The native receives `receiver` ahead of the call's own arguments; its arity
+/// counts only the latter.
+pub struct Method {
+  pub receiver: Eval,
+  pub native: Rc<dyn NativeFn>
+}
+
+/// A named set of values reached with `.`, such as the `math` library.
+pub struct Module {
+  pub name: String,
+  pub members: HashMap<String, Eval>
+}
+
+/// A user-defined iterator, as made by `iterator( has_next, next )`.
+///
+/// A for-in loop calls `has_next()` before each pass and binds whatever `next()`
+/// returns, nil included.
+pub struct Cursor {
+  pub has_next: Eval,
+  pub next: Eval
 }
 
 impl Eval {
@@ -27,7 +57,29 @@ impl Eval {
       Eval::StringLiteral( _ ) => "String".to_string(),
       Eval::Bool( _ ) => "Bool".to_string(),
       Eval::Nil => "Nil".to_string(),
-      Eval::Fun( f, _ ) => format!( "fun<{}>", f.params.len() )
+      Eval::Fun( f, _ ) => format!( "fun<{}>", f.params.len() ),
+      Eval::List( _ ) => "List".to_string(),
+      Eval::Map( _ ) => "Map".to_string(),
+      Eval::Native( native ) => format!( "native<{}>", native.arity() ),
+      Eval::Method( method ) => format!( "native<{}>", method.native.arity() ),
+      Eval::Module( _ ) => "Module".to_string(),
+      Eval::Iterator( _ ) => "Iterator".to_string()
+    }
+  }
+
+  /// The bytes this value holds beyond its own slot [ elements are counted when they're made ].
+  pub fn footprint( &self ) -> usize {
+    match self {
+      Eval::StringLiteral( s ) => s.len(),
+      Eval::List( list ) => list.view().len() * std::mem::size_of::<Eval>(),
+      Eval::Map( map ) => map.view().len() * ( std::mem::size_of::<MapKey>() + std::mem::size_of::<Eval>() ),
+      _ => 0
+    }
+  }
+  pub fn repr( &self ) -> String {
+    match self {
+      Eval::StringLiteral( s ) => format!( "\"{}\"", s ),
+      _ => self.to_string()
     }
   }
 }
@@ -40,6 +92,25 @@ impl Display for Eval {
       Eval::Bool( b ) => write!( f, "{}", b ),
       Eval::Nil => write!( f, "nil" ),
       Eval::Fun( function, _ ) => write!( f, "{}<{}>()", function.name.lexeme, function.params.len() ),
+      Eval::List( list ) => {
+        let elements: Vec<String> = list.view().iter().map( | e | e.repr() ).collect();
+        write!( f, "[{}]", elements.join( ", " ) )
+      },
+      Eval::Map( map ) => {
+        let entries: Vec<String> = map.view().iter()
+          .map( | ( key, value ) | format!( "{}: {}", key, value.repr() ) ).collect();
+        write!( f, "{{{}}}", entries.join( ", " ) )
+      },
+      Eval::Native( native ) => write!( f, "<native {}<{}>()>", native.name(), native.arity() ),
+      Eval::Method( method ) => write!( f, "<method {}.{}<{}>()>",
+        method.receiver.get_type_name(), method.native.name(), method.native.arity() ),
+      Eval::Module( module ) => write!( f, "<module {}>", module.name ),
+      Eval::Iterator( _ ) => write!( f, "<iterator>" )
     }
   }
-}
\ No newline at end of file
+}
+impl Debug for Eval {
+  fn fmt( &self, f: &mut std::fmt::Formatter<'_> ) -> std::fmt::Result {
+    write!( f, "{}", self.repr() )
+  }
+}
@@ -1,14 +1,20 @@
 
-use crate::token::Token;
+use crate::{stmt::Function, token::Token};
 
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub enum Expr {
   Assign( Assign ),
   Binary( Binary ),
   Call( Call ),
+  Get( Get ),
   Grouping( Grouping ),
+  Index( Index ),
+  Lambda( Lambda ),
+  List( List ),
   Literal( Literal ),
+  Map( Map ),
   // Logical( Logical ),
+  SetIndex( SetIndex ),
   Unary( Unary ),
   Variable( Variable )
 }
@@ -17,9 +23,15 @@ pub trait Visitor<R> {
   fn visit_assign_expr( &mut self, assign: &Assign ) -> R;
   fn visit_binary_expr( &mut self, binary: &Binary ) -> R;
   fn visit_call_expr( &mut self, call: &Call ) -> R;
+  fn visit_get_expr( &mut self, get: &Get ) -> R;
   fn visit_grouping_expr( &mut self, grouping: &Grouping ) -> R;
+  fn visit_index_expr( &mut self, index: &Index ) -> R;
+  fn visit_lambda_expr( &mut self, lambda: &Lambda ) -> R;
+  fn visit_list_expr( &mut self, list: &List ) -> R;
   fn visit_literal_expr( &mut self, literal: &Literal ) -> R;
+  fn visit_map_expr( &mut self, map: &Map ) -> R;
   // fn visit_logical_expr( &mut self, logical: &Logical ) -> R;
+  fn visit_set_index_expr( &mut self, set_index: &SetIndex ) -> R;
   fn visit_unary_expr( &mut self, unary: &Unary ) -> R;
   fn visit_variable_expr( &mut self, variable: &Variable ) -> R;
 }
@@ -28,57 +40,106 @@ pub trait MutVisitor<R> {
   fn visit_assign_expr_mut( &mut self, assign: &mut Assign ) -> R;
   fn visit_binary_expr_mut( &mut self, binary: &mut Binary ) -> R;
   fn visit_call_expr_mut( &mut self, call: &mut Call ) -> R;
+  fn visit_get_expr_mut( &mut self, get: &mut Get ) -> R;
   fn visit_grouping_expr_mut( &mut self, grouping: &mut Grouping ) -> R;
+  fn visit_index_expr_mut( &mut self, index: &mut Index ) -> R;
+  fn visit_lambda_expr_mut( &mut self, lambda: &mut Lambda ) -> R;
+  fn visit_list_expr_mut( &mut self, list: &mut List ) -> R;
   fn visit_literal_expr_mut( &mut self, literal: &mut Literal ) -> R;
+  fn visit_map_expr_mut( &mut self, map: &mut Map ) -> R;
   // fn visit_logical_expr_mut( &mut self, logical: &mut Logical ) -> R;
+  fn visit_set_index_expr_mut( &mut self, set_index: &mut SetIndex ) -> R;
   fn visit_unary_expr_mut( &mut self, unary: &mut Unary ) -> R;
   fn visit_variable_expr_mut( &mut self, variable: &mut Variable ) -> R;
 }
 
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct Assign {
   pub lhs: Variable,
   pub rhs: Box<Expr>
 }
 
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct Binary {
   pub left: Box<Expr>,
   pub operator: Token,
   pub right: Box<Expr>
 }
 
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct Call {
   pub callee: Box<Expr>,
   pub paren: Token,
   pub arguments: Vec<Box<Expr>>
 }
 
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug)]
+pub struct Get {
+  pub object: Box<Expr>,
+  pub name: Token
+}
+
+#[derive(Clone, Debug)]
 pub struct Grouping {
   pub expression: Box<Expr>
 }
 
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug)]
+pub struct Index {
+  pub object: Box<Expr>,
+  pub bracket: Token,
+  pub index: Box<Expr>
+}
+
+/// An anonymous function, `fun ( a ) { ... }` or `( a ) => ...`.
+///
+/// The arrow form's body is a single return of its expression. The function's
+/// name is a made-up `lambda` token on the keyword's line.
+#[derive(Clone, Debug)]
+pub struct Lambda {
+  pub keyword: Token,
+  pub function: Function
+}
+
+#[derive(Clone, Debug)]
+pub struct List {
+  pub bracket: Token,
+  pub elements: Vec<Box<Expr>>
+}
+
+#[derive(Clone, Debug)]
 pub struct Literal {
   pub value: Token
 }
 
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug)]
+pub struct Map {
+  pub brace: Token,
+  pub entries: Vec<( Box<Expr>, Box<Expr> )>
+}
+
+#[derive(Clone, Debug)]
 pub struct Logical {
   pub left: Box<Expr>,
   pub operator: Token,
   pub right: Box<Expr>
 }
 
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug)]
+pub struct SetIndex {
+  pub object: Box<Expr>,
+  pub bracket: Token,
+  pub index: Box<Expr>,
+  pub value: Box<Expr>
+}
+
+#[derive(Clone, Debug)]
 pub struct Unary {
   pub operator: Token,
   pub right: Box<Expr>
 }
 
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct Variable {
   pub name: Token,
   pub jump: i32
@@ -90,9 +151,15 @@ impl Expr {
       Expr::Assign( assign ) => visitor.visit_assign_expr( assign ),
       Expr::Binary( binary ) => visitor.visit_binary_expr( binary ),
       Expr::Call( call ) => visitor.visit_call_expr( call ),
+      Expr::Get( get ) => visitor.visit_get_expr( get ),
       Expr::Grouping( grouping ) => visitor.visit_grouping_expr( grouping ),
+      Expr::Index( index ) => visitor.visit_index_expr( index ),
+      Expr::Lambda( lambda ) => visitor.visit_lambda_expr( lambda ),
+      Expr::List( list ) => visitor.visit_list_expr( list ),
       Expr::Literal( literal ) => visitor.visit_literal_expr( literal ),
+      Expr::Map( map ) => visitor.visit_map_expr( map ),
       // Expr::Logical( logical ) => visitor.visit_logical_expr( logical ),
+      Expr::SetIndex( set_index ) => visitor.visit_set_index_expr( set_index ),
       Expr::Unary( unary ) => visitor.visit_unary_expr( unary ),
       Expr::Variable( variable ) => visitor.visit_variable_expr( variable ),
     }
@@ -102,9 +169,15 @@ impl Expr {
       Expr::Assign( assign ) => visitor.visit_assign_expr_mut( assign ),
       Expr::Binary( binary ) => visitor.visit_binary_expr_mut( binary ),
       Expr::Call( call ) => visitor.visit_call_expr_mut( call ),
+      Expr::Get( get ) => visitor.visit_get_expr_mut( get ),
       Expr::Grouping( grouping ) => visitor.visit_grouping_expr_mut( grouping ),
+      Expr::Index( index ) => visitor.visit_index_expr_mut( index ),
+      Expr::Lambda( lambda ) => visitor.visit_lambda_expr_mut( lambda ),
+      Expr::List( list ) => visitor.visit_list_expr_mut( list ),
       Expr::Literal( literal ) => visitor.visit_literal_expr_mut( literal ),
+      Expr::Map( map ) => visitor.visit_map_expr_mut( map ),
       // Expr::Logical( logical ) => visitor.visit_logical_expr_mut( logical ),
+      Expr::SetIndex( set_index ) => visitor.visit_set_index_expr_mut( set_index ),
       Expr::Unary( unary ) => visitor.visit_unary_expr_mut( unary ),
       Expr::Variable( variable ) => visitor.visit_variable_expr_mut( variable ),
     }
@@ -0,0 +1,343 @@
+////////////////////////////////////
+// private module rlox::formatter //
+////////////////////////////////////
+
+
+/////////
+// use //
+/////////
+
+use crate::expr::{self, Expr};
+use crate::stmt::{self, Stmt};
+use crate::token::{Token, TokenType};
+
+
+//////////////////
+// declarations //
+//////////////////
+
+/// Re-emits parsed statements in the canonical layout: two-space indents, braces
+/// on the line they open, single spaces around operators and after commas.
+///
+/// Comments aren't part of the tree, so they're woven back in by line: those
+/// before a statement go above it, one on the line a statement ends goes after it.
+pub struct Formatter {
+  comments: Vec<Token>,
+
+  // the first comment not yet emitted
+  next: usize,
+  indent: usize,
+
+  // the last source line emitted so far
+  last_line: u32
+}
+
+
+/////////////////////
+// implementations //
+/////////////////////
+
+impl Formatter {
+  pub fn new( comments: Vec<Token> ) -> Formatter {
+    Formatter {
+      comments,
+      next: 0,
+      indent: 0,
+      last_line: 0
+    }
+  }
+  pub fn format( &mut self, stmts: &[Stmt] ) -> String {
+    self.statements( stmts, u32::MAX )
+  }
+
+  // the statements of a block ending on line `end`, each on its own line
+  fn statements( &mut self, stmts: &[Stmt], end: u32 ) -> String {
+    let mut out = String::new();
+    for ( idx, stmt ) in stmts.iter().enumerate() {
+      let line = stmt_line( stmt );
+      out += &self.comments_before( line, !out.is_empty() );
+      if !out.is_empty() && line > self.last_line + 1 {
+        out += "\n";
+      }
+      out += &self.pad();
+      out += &stmt.accept( self );
+
+      // a comment trailing the statement, unless the next one or the closing brace shares its line
+      let next_line = stmts.get( idx + 1 ).map( stmt_line ).unwrap_or( end );
+      if let Some( comment ) = self.comments.get( self.next ) {
+        if comment.line == self.last_line && next_line > self.last_line {
+          out += " ";
+          out += &comment.lexeme;
+          self.next += 1;
+        }
+      }
+      out += "\n";
+    }
+    let started = !out.is_empty();
+    out + &self.comments_before( end, started )
+  }
+
+  // the comments not yet emitted that come before `line`, each on its own line
+  fn comments_before( &mut self, line: u32, started: bool ) -> String {
+    let mut out = String::new();
+    while let Some( comment ) = self.comments.get( self.next ) {
+      if comment.line >= line {
+        break;
+      }
+      if ( started || !out.is_empty() ) && comment.line > self.last_line + 1 {
+        out += "\n";
+      }
+      out += &format!( "{}{}\n", self.pad(), comment.lexeme );
+      self.last_line = comment.line;
+      self.next += 1;
+    }
+    out
+  }
+
+  // "{ ... }" around statements ending on line `end`
+  fn block( &mut self, stmts: &[Stmt], end: u32 ) -> String {
+    self.indent += 1;
+    let body = self.statements( stmts, end );
+    self.indent -= 1;
+    self.last_line = self.last_line.max( end );
+    if body.is_empty() {
+      "{}".into()
+    } else {
+      format!( "{{\n{}{}}}", body, self.pad() )
+    }
+  }
+  fn pad( &self ) -> String {
+    "  ".repeat( self.indent )
+  }
+
+  // a token's text, noting how far into the source we are
+  fn token( &mut self, token: &Token ) -> String {
+    self.last_line = self.last_line.max( token.line );
+    match token.token_type {
+      TokenType::String => format!( "\"{}\"", token.lexeme ),
+      _ => token.lexeme.clone()
+    }
+  }
+  fn expr( &mut self, expr: &Expr ) -> String {
+    expr.accept( self )
+  }
+  fn exprs( &mut self, exprs: &[Box<Expr>] ) -> String {
+    let exprs: Vec<String> = exprs.iter().map( | expr | self.expr( expr ) ).collect();
+    exprs.join( ", " )
+  }
+  fn params( &mut self, params: &[Token] ) -> String {
+    let params: Vec<String> = params.iter().map( | param | self.token( param ) ).collect();
+    format!( "({})", params.join( ", " ) )
+  }
+
+  // the for-loop the parser desugared into `{ init; while ... }`, if that's what `block` is
+  fn desugared_for( block: &stmt::Block ) -> Option<( &Stmt, &stmt::While )> {
+    match block.statements.as_slice() {
+      [ init, Stmt::While( while_ ) ] if while_.keyword.token_type == TokenType::For
+        && while_.keyword.line == block.line && block.end == block.line => Some( ( init, while_ ) ),
+      _ => None
+    }
+  }
+  fn for_loop( &mut self, init: Option<&Stmt>, while_: &stmt::While ) -> String {
+    let keyword = self.token( &while_.keyword );
+    let init = match init {
+      Some( init ) => init.accept( self ),
+      None => ";".into()
+    };
+    let condition = self.expr( &while_.condition );
+    let increment = match &while_.increment {
+      Some( increment ) => format!( " {}", self.expr( increment ) ),
+      None => String::new()
+    };
+    format!( "{} ({} {};{}) {}", keyword, init, condition, increment, while_.body.accept( self ) )
+  }
+}
+
+impl stmt::Visitor<String> for Formatter {
+  fn visit_block_stmt( &mut self, block: &stmt::Block ) -> String {
+    match Formatter::desugared_for( block ) {
+      Some( ( init, while_ ) ) => self.for_loop( Some( init ), while_ ),
+      None => self.block( &block.statements, block.end )
+    }
+  }
+  fn visit_break_stmt( &mut self, break_: &stmt::Break ) -> String {
+    format!( "{};", self.token( &break_.keyword ) )
+  }
+  fn visit_continue_stmt( &mut self, continue_: &stmt::Continue ) -> String {
+    format!( "{};", self.token( &continue_.keyword ) )
+  }
+  fn visit_expression_stmt( &mut self, expression: &stmt::Expression ) -> String {
+    format!( "{};", self.expr( &expression.expression ) )
+  }
+  fn visit_for_in_stmt( &mut self, for_in: &stmt::ForIn ) -> String {
+    let keyword = self.token( &for_in.keyword );
+    let name = self.token( &for_in.name );
+    let iterable = self.expr( &for_in.iterable );
+    format!( "{} ({} in {}) {}", keyword, name, iterable, for_in.body.accept( self ) )
+  }
+  fn visit_function_stmt( &mut self, function: &stmt::Function ) -> String {
+    let name = self.token( &function.name );
+    let params = self.params( &function.params );
+    format!( "fun {}{} {}", name, params, self.block( &function.body, function.end ) )
+  }
+  fn visit_if_stmt( &mut self, if_: &stmt::If ) -> String {
+    let condition = self.expr( &if_.condition );
+    let mut out = format!( "if ({}) {}", condition, if_.then_branch.accept( self ) );
+    if let Some( else_branch ) = &if_.else_branch {
+      out += " else ";
+      out += &else_branch.accept( self );
+    }
+    out
+  }
+  fn visit_print_stmt( &mut self, print: &stmt::Print ) -> String {
+    format!( "print {};", self.expr( &print.expression ) )
+  }
+  fn visit_return_stmt( &mut self, return_: &stmt::Return ) -> String {
+    let keyword = self.token( &return_.keyword );
+    match &return_.value {
+      Some( value ) => format!( "{} {};", keyword, self.expr( value ) ),
+      None => format!( "{};", keyword )
+    }
+  }
+  fn visit_throw_stmt( &mut self, throw: &stmt::Throw ) -> String {
+    let keyword = self.token( &throw.keyword );
+    format!( "{} {};", keyword, self.expr( &throw.value ) )
+  }
+  fn visit_try_stmt( &mut self, try_: &stmt::Try ) -> String {
+    let keyword = self.token( &try_.keyword );
+    let mut out = format!( "{} {}", keyword, self.block( &try_.body.statements, try_.body.end ) );
+    if let Some( catch ) = &try_.catch {
+      let name = self.token( &catch.name );
+      out += &format!( " catch ({}) {}", name, self.block( &catch.body.statements, catch.body.end ) );
+    }
+    if let Some( finally ) = &try_.finally {
+      out += &format!( " finally {}", self.block( &finally.statements, finally.end ) );
+    }
+    out
+  }
+  fn visit_var_stmt( &mut self, var: &stmt::Var ) -> String {
+    let name = self.token( &var.name );
+    match &var.init {
+      Some( init ) => format!( "var {} = {};", name, self.expr( init ) ),
+      None => format!( "var {};", name )
+    }
+  }
+  fn visit_while_stmt( &mut self, while_: &stmt::While ) -> String {
+    if while_.keyword.token_type == TokenType::For {
+      return self.for_loop( None, while_ );
+    }
+    let keyword = self.token( &while_.keyword );
+    let condition = self.expr( &while_.condition );
+    format!( "{} ({}) {}", keyword, condition, while_.body.accept( self ) )
+  }
+}
+
+impl expr::Visitor<String> for Formatter {
+  fn visit_assign_expr( &mut self, assign: &expr::Assign ) -> String {
+    let lhs = self.token( &assign.lhs.name );
+    format!( "{} = {}", lhs, self.expr( &assign.rhs ) )
+  }
+  fn visit_binary_expr( &mut self, binary: &expr::Binary ) -> String {
+    let left = self.expr( &binary.left );
+    let operator = self.token( &binary.operator );
+    format!( "{} {} {}", left, operator, self.expr( &binary.right ) )
+  }
+  fn visit_call_expr( &mut self, call: &expr::Call ) -> String {
+    let callee = self.expr( &call.callee );
+    let arguments = self.exprs( &call.arguments );
+    self.token( &call.paren );
+    format!( "{}({})", callee, arguments )
+  }
+  fn visit_get_expr( &mut self, get: &expr::Get ) -> String {
+    let object = self.expr( &get.object );
+    format!( "{}.{}", object, self.token( &get.name ) )
+  }
+  fn visit_grouping_expr( &mut self, grouping: &expr::Grouping ) -> String {
+    format!( "({})", self.expr( &grouping.expression ) )
+  }
+  fn visit_index_expr( &mut self, index: &expr::Index ) -> String {
+    let object = self.expr( &index.object );
+    let idx = self.expr( &index.index );
+    self.token( &index.bracket );
+    format!( "{}[{}]", object, idx )
+  }
+  fn visit_lambda_expr( &mut self, lambda: &expr::Lambda ) -> String {
+    let function = &lambda.function;
+    let params = self.params( &function.params );
+    match ( lambda.keyword.token_type, function.body.as_slice() ) {
+
+      // "( a ) => expr", whose body is the return of expr
+      ( TokenType::Arrow, [ Stmt::Return( stmt::Return { value: Some( value ), .. } ) ] ) => {
+        self.token( &lambda.keyword );
+        format!( "{} => {}", params, self.expr( value ) )
+      },
+      _ => format!( "fun {} {}", params, self.block( &function.body, function.end ) )
+    }
+  }
+  fn visit_list_expr( &mut self, list: &expr::List ) -> String {
+    self.token( &list.bracket );
+    format!( "[{}]", self.exprs( &list.elements ) )
+  }
+  fn visit_literal_expr( &mut self, literal: &expr::Literal ) -> String {
+    self.token( &literal.value )
+  }
+  fn visit_map_expr( &mut self, map: &expr::Map ) -> String {
+    self.token( &map.brace );
+    let entries: Vec<String> = map.entries.iter().map( | ( key, value ) | {
+      let key = self.expr( key );
+      format!( "{}: {}", key, self.expr( value ) )
+    } ).collect();
+    format!( "{{{}}}", entries.join( ", " ) )
+  }
+  fn visit_set_index_expr( &mut self, set_index: &expr::SetIndex ) -> String {
+    let object = self.expr( &set_index.object );
+    let index = self.expr( &set_index.index );
+    self.token( &set_index.bracket );
+    format!( "{}[{}] = {}", object, index, self.expr( &set_index.value ) )
+  }
+  fn visit_unary_expr( &mut self, unary: &expr::Unary ) -> String {
+    let operator = self.token( &unary.operator );
+    format!( "{}{}", operator, self.expr( &unary.right ) )
+  }
+  fn visit_variable_expr( &mut self, variable: &expr::Variable ) -> String {
+    self.token( &variable.name )
+  }
+}
+
+// the line a statement starts on
+fn stmt_line( stmt: &Stmt ) -> u32 {
+  match stmt {
+    Stmt::Block( block ) => block.line,
+    Stmt::Break( break_ ) => break_.keyword.line,
+    Stmt::Continue( continue_ ) => continue_.keyword.line,
+    Stmt::Expression( expression ) => expr_line( &expression.expression ),
+    Stmt::ForIn( for_in ) => for_in.keyword.line,
+    Stmt::Function( function ) => function.name.line,
+    Stmt::If( if_ ) => expr_line( &if_.condition ),
+    Stmt::Print( print ) => expr_line( &print.expression ),
+    Stmt::Return( return_ ) => return_.keyword.line,
+    Stmt::Throw( throw ) => throw.keyword.line,
+    Stmt::Try( try_ ) => try_.keyword.line,
+    Stmt::Var( var ) => var.name.line,
+    Stmt::While( while_ ) => while_.keyword.line
+  }
+}
+
+// the line of an expression's leftmost token
+pub(crate) fn expr_line( expr: &Expr ) -> u32 {
+  match expr {
+    Expr::Assign( assign ) => assign.lhs.name.line,
+    Expr::Binary( binary ) => expr_line( &binary.left ),
+    Expr::Call( call ) => expr_line( &call.callee ),
+    Expr::Get( get ) => expr_line( &get.object ),
+    Expr::Grouping( grouping ) => expr_line( &grouping.expression ),
+    Expr::Index( index ) => expr_line( &index.object ),
+    Expr::Lambda( lambda ) => lambda.function.params.first().unwrap_or( &lambda.keyword ).line,
+    Expr::List( list ) => list.bracket.line,
+    Expr::Literal( literal ) => literal.value.line,
+    Expr::Map( map ) => map.brace.line,
+    Expr::SetIndex( set_index ) => expr_line( &set_index.object ),
+    Expr::Unary( unary ) => unary.operator.line,
+    Expr::Variable( variable ) => variable.name.line
+  }
+}
@@ -7,22 +7,83 @@
 // use //
 /////////
 
-use crate::{env::Env, error::Error, eval::Eval, expr::{self, Expr},
-stmt::{self, Stmt}, token::TokenType, util::{assert, RcMut}};
+use std::{collections::{BTreeMap, HashSet}, io::{self, Write}, rc::Rc, sync::{atomic::AtomicBool, Arc}};
+
+use crate::{env::Env, error::Error, eval::{Cursor, Eval, Method}, expr::{self, Expr},
+lists, maps::{self, MapKey}, stmt::{self, Stmt}, strings, token::{Token, TokenType}, util::{assert, Fuel, Memory, RcMut}};
 
 
 //////////////////
 // declarations //
 //////////////////
 
+/// The native stack scripts run on [ see `rlox::with_stack` ].
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// The native stack one call may take, generously: the tree-walker recurses through
+/// the statements and expressions of every function on the way to the next call.
+const STACK_PER_CALL: usize = 64 * 1024;
+
+/// The most calls `STACK_SIZE` leaves room for, and so the highest `--max-depth` allowed.
+pub const MAX_DEPTH: usize = STACK_SIZE / STACK_PER_CALL;
+
+/// How many calls may be in progress at once before a script fails with a stack overflow.
+pub const DEFAULT_MAX_DEPTH: usize = MAX_DEPTH / 4;
+
+/// The least stack a host's own thread can be counted on to have: what Rust gives a spawned thread.
+pub const HOST_STACK_SIZE: usize = 2 * 1024 * 1024;
+
+/// How deeply an `Engine` lets calls nest by default, as it runs on the host's stack.
+pub const ENGINE_MAX_DEPTH: usize = HOST_STACK_SIZE / STACK_PER_CALL;
+
 pub struct Interpreter {
   envs: RcMut<Env>,
+
+  // the function name and call-site line of each call in progress, outermost first
+  calls: Vec<( String, u32 )>,
+
+  // the environment each call in progress returns to [ still live, for the memory count ]
+  callers: Vec<RcMut<Env>>,
+  max_depth: usize,
+
+  // spent one unit per statement
+  fuel: Fuel,
+  memory: Memory,
+
+  // the line of the block last entered, to place errors that have no token
+  line: u32,
+
+  // where print statements write to [ stdout unless the host says otherwise ]
+  out: Box<dyn Write>,
+
+  // the code the script asked to exit with, leaving it to the host to act on
+  exit_code: Option<i32>,
+  errors: Vec<Error>,
   had_error: bool
 }
 
 pub enum EvalError {
   Error( Error ),
-  Return( Eval )
+
+  // a thrown value, and the error to report if nothing catches it
+  Throw( Eval, Box<Error> ),
+
+  // stops the script outright, past any try blocks
+  Halt( Error ),
+
+  // as Halt, but the script asked for it [ see exit_code ]
+  Exit,
+  Return( Eval ),
+  Break,
+  Continue
+}
+
+/// Where a for-in loop gets its values from.
+enum Iteration {
+  Items( std::vec::IntoIter<Eval> ),
+
+  // the iterator protocol: call `next` for as long as `has_next` says so
+  Protocol( Rc<Cursor> )
 }
 
 
@@ -34,15 +95,106 @@ impl Interpreter {
   pub fn new() -> Interpreter {
     Interpreter {
       envs: Env::create_global(),
+      calls: Vec::new(),
+      callers: Vec::new(),
+      max_depth: DEFAULT_MAX_DEPTH,
+      fuel: Fuel::new(),
+      memory: Memory::new(),
+      line: 0,
+      out: Box::new( io::stdout() ),
+      exit_code: None,
+      errors: Vec::new(),
       had_error: false
     }
   }
   pub fn restart( &mut self ) {
     self.envs = Env::create_global();
+    self.calls.clear();
+    self.callers.clear();
+    self.fuel.refill();
+    self.memory.reset();
+    self.exit_code = None;
+    self.errors.clear();
     self.had_error = false;
   }
+
+  /// Stops the running script once the current native call returns, as `exit( code )` does.
+  pub fn exit( &mut self, code: i32 ) {
+    self.exit_code = Some( code );
+  }
+
+  /// The code the last run asked to exit with, if it did.
+  pub fn exit_code( &self ) -> Option<i32> {
+    self.exit_code
+  }
+
+  /// Limits how deeply calls may nest, so runaway recursion fails instead of exhausting the host's stack.
+  pub fn set_max_depth( &mut self, max_depth: usize ) {
+    self.max_depth = max_depth;
+  }
+
+  /// Limits each run to `budget` statements [ None for no limit ].
+  pub fn set_budget( &mut self, budget: Option<u64> ) {
+    self.fuel.set_limit( budget );
+  }
+
+  /// A flag that, once set from any thread, stops the script at its next statement.
+  pub fn interrupt_handle( &self ) -> Arc<AtomicBool> {
+    self.fuel.interrupt_handle()
+  }
+
+  /// Caps the bytes each run may hold in strings, collections and bindings [ None for the built-in ceiling ].
+  pub fn set_memory_limit( &mut self, limit: Option<usize> ) {
+    self.memory.set_limit( limit );
+  }
+
+  /// Makes sure `bytes` more fit under the memory cap, for natives that build large values in one go.
+  ///
+  /// Nothing is charged: the call charges the value the native returns.
+  pub fn make_room( &mut self, bytes: usize ) -> Result<(), Error> {
+    if bytes > self.memory.room() {
+      let live = self.live_size();
+      self.memory.settle( live );
+      if bytes > self.memory.room() {
+        return Err( Error::from_msg( "Out of memory.".into() ) );
+      }
+    }
+    Ok( () )
+  }
+
+  /// Charges a native's work against the budget before it does it, failing if that runs the budget out.
+  pub fn spend( &mut self, steps: u64 ) -> Result<(), Error> {
+    match self.fuel.burn( steps ) {
+      Some( reason ) => Err( Error::from_msg( reason.into() ) ),
+      None => Ok( () )
+    }
+  }
+
+  /// Sends the output of print statements to `out`.
+  pub fn set_output( &mut self, out: Box<dyn Write> ) {
+    self.out = out;
+  }
+  pub fn flush_output( &mut self ) {
+    let _ = self.out.flush();
+  }
+  pub fn take_errors( &mut self ) -> Vec<Error> {
+    std::mem::take( &mut self.errors )
+  }
+  pub fn define_global( &mut self, name: &str, value: Eval ) {
+    let name = Token { token_type: TokenType::Identifier, lexeme: name.into(), line: 0 };
+    self.envs.view_mut().define_symbol( &name, &value );
+  }
+  pub fn global( &self, name: &str ) -> Option<Eval> {
+    self.envs.view().get_symbol( name )
+  }
+  pub fn globals( &self ) -> Vec<( String, Eval )> {
+    self.envs.view().symbols()
+  }
   pub fn interpret( &mut self, stmts: &Vec<Stmt> ) -> ( Eval, bool ) {
-    self.restart();
+    self.had_error = false;
+    self.exit_code = None;
+    self.fuel.refill();
+    self.memory.reset();
     let mut result = Eval::Nil;
     for stmt in stmts {
       match self.interpret_stmt( stmt ) {
@@ -51,25 +203,300 @@ impl Interpreter {
           self.emit_error( &e );
           return ( result, self.had_error )
         },
+        Err( EvalError::Throw( _, e ) ) => {
+          self.emit_error( &e );
+          return ( result, self.had_error )
+        },
+        Err( EvalError::Halt( e ) ) => {
+          self.emit_error( &e );
+          return ( result, self.had_error )
+        },
+        Err( EvalError::Exit ) => break,
         Err( EvalError::Return( _ ) ) => {
           panic!( "Internal error: Return values shouldn't make it here." );
+        },
+        Err( EvalError::Break | EvalError::Continue ) => {
+          panic!( "Internal error: The resolver only allows 'break' and 'continue' inside loops." );
         }
       }
     }
+    self.flush_output();
     ( result, self.had_error )
   }
+  pub fn call_function( &mut self, callee: Eval, args: Vec<Eval>, name: &Token ) -> ( Eval, bool ) {
+    self.had_error = false;
+    self.exit_code = None;
+    self.fuel.refill();
+    self.memory.reset();
+    match self.call( callee, args, name ) {
+      Ok( eval ) => {
+        self.flush_output();
+        ( eval, self.had_error )
+      },
+      Err( EvalError::Error( e ) ) => {
+        self.emit_error( &e );
+        ( Eval::Nil, self.had_error )
+      },
+      Err( EvalError::Throw( _, e ) ) => {
+        self.emit_error( &e );
+        ( Eval::Nil, self.had_error )
+      },
+      Err( EvalError::Halt( e ) ) => {
+        self.emit_error( &e );
+        ( Eval::Nil, self.had_error )
+      },
+      Err( EvalError::Exit ) => {
+        self.flush_output();
+        ( Eval::Nil, self.had_error )
+      },
+      Err( EvalError::Return( _ ) ) => {
+        panic!( "Internal error: Return values shouldn't make it here." );
+      },
+      Err( EvalError::Break | EvalError::Continue ) => {
+        panic!( "Internal error: The resolver only allows 'break' and 'continue' inside loops." );
+      }
+    }
+  }
+
+  /// Accounts for `bytes` allocated on behalf of `token`, failing if they don't fit
+  /// even once the count is settled to what the run still holds.
+  fn allocate( &mut self, bytes: usize, token: &Token ) -> Result<(), EvalError> {
+    if !self.memory.charge( bytes ) {
+      let live = self.live_size();
+      self.memory.settle( live );
+      if !self.memory.charge( bytes ) {
+        return Err( EvalError::Error( Error::from_token( token, "Out of memory.".into() ) ) );
+      }
+    }
+    Ok( () )
+  }
+
+  /// The bytes held by everything the run can still reach, counted as `allocate` charges them.
+  ///
+  /// [ values in flight, such as the operands of an expression being evaluated, aren't counted ]
+  fn live_size( &self ) -> usize {
+    let mut seen: HashSet<usize> = HashSet::new();
+    let mut envs = self.callers.clone();
+    envs.push( self.envs.clone() );
+    let mut values: Vec<Eval> = Vec::new();
+    let mut bytes = 0;
+    loop {
+      if let Some( env ) = envs.pop() {
+        if !seen.insert( env.addr() ) {
+          continue;
+        }
+        let env = env.view();
+        for ( name, value ) in env.bindings() {
+          bytes += name.len() + std::mem::size_of::<Eval>();
+          reach( value, &mut bytes, &mut envs, &mut values );
+        }
+        envs.extend( env.enclosing().cloned() );
+      } else if let Some( value ) = values.pop() {
+        match &value {
+          Eval::List( list ) if seen.insert( list.addr() ) => {
+            bytes += value.footprint();
+            for item in list.view().iter() {
+              reach( item, &mut bytes, &mut envs, &mut values );
+            }
+          },
+          Eval::Map( map ) if seen.insert( map.addr() ) => {
+            bytes += value.footprint();
+            for item in map.view().values() {
+              reach( item, &mut bytes, &mut envs, &mut values );
+            }
+          },
+          Eval::Method( method ) => reach( &method.receiver, &mut bytes, &mut envs, &mut values ),
+          Eval::Module( module ) => for member in module.members.values() {
+            reach( member, &mut bytes, &mut envs, &mut values );
+          },
+          Eval::Iterator( cursor ) => {
+            reach( &cursor.has_next, &mut bytes, &mut envs, &mut values );
+            reach( &cursor.next, &mut bytes, &mut envs, &mut values );
+          },
+          _ => {}
+        }
+      } else {
+        return bytes;
+      }
+    }
+  }
+
+  /// Binds `name` in the innermost environment.
+  ///
+  /// Globals may be bound again, as a session [ the REPL, an `Engine` ] redeclares them.
+  fn bind( &mut self, name: &Token, value: &Eval ) -> Result<(), EvalError> {
+    self.allocate( binding_size( name ), name )?;
+    let mut env = self.envs.view_mut();
+    if env.is_global() {
+      env.define_symbol( name, value );
+    } else {
+      env.create_symbol( name, value );
+    }
+    Ok( () )
+  }
   fn emit_error( &mut self, error: &Error ) {
-    eprintln!( "[line {}] Error{}: {}", error.line, error.loc, error.msg );
+    self.flush_output();
+    self.errors.push( error.clone() );
     self.had_error = true;
   }
+  fn call( &mut self, callee: Eval, args: Vec<Eval>, paren: &Token ) -> Result<Eval, EvalError> {
+
+    // if working correctly, callee will be an Eval::Fun
+    // from which we can invoke the function call.
+    if let Eval::Fun( f, closure ) = callee {
+
+      // check arity
+      if f.params.len() != args.len() {
+        return Err( EvalError::Error( Error::from_token( paren,
+          format!( "Expected {} arguments to function call, but found {}.", f.params.len(),
+            args.len() ) ) ) );
+      }
+
+      // every call nests the host's stack too
+      if self.calls.len() >= self.max_depth {
+        return Err( self.traced( EvalError::Error( Error::from_token( paren, "Stack overflow.".into() ) ) ) );
+      }
+
+      // prepare function scope
+      let bytes = f.params.iter().map( binding_size ).sum();
+      self.allocate( bytes, paren )?;
+      self.callers.push( self.envs.clone() );
+      self.envs = Env::new_with_enclosing( &closure );
+      for ( param, arg ) in std::iter::zip( f.params, args ) {
+        self.envs.view_mut().create_symbol( &param, &arg );
+      }
+
+      // execute [ recording the call for stack traces ]
+      self.calls.push( ( f.name.lexeme.clone(), paren.line ) );
+      let mut exec_result = Ok( Eval::Nil );
+      for stmt in &f.body {
+        match self.interpret_stmt( stmt ) {
+          Ok( result ) => {
+            exec_result = Ok( result );
+          },
+          Err( EvalError::Return( retval ) ) => {
+            exec_result = Ok( retval );
+            break;
+          },
+          Err( actual_error ) => {
+            exec_result = Err( self.traced( actual_error ) );
+            break;
+          }
+        }
+      };
+      self.calls.pop();
+      self.envs = Env::drop_enclosed( &self.envs );
+      self.envs = self.callers.pop().unwrap();
+      exec_result
+    } else if let Eval::Native( native ) = callee {
+
+      // check arity
+      if !native.arity().accepts( args.len() ) {
+        return Err( EvalError::Error( Error::from_token( paren,
+          format!( "Expected {} arguments to function call, but found {}.", native.arity(),
+            args.len() ) ) ) );
+      }
+
+      // errors without a line of their own belong to the call site
+      let result = native.call( self, &args ).map_err( | e | self.native_error( e, paren ) )?;
+      if self.exit_code.is_some() {
+        return Err( EvalError::Exit );
+      }
+      self.allocate( result.footprint(), paren )?;
+      Ok( result )
+    } else if let Eval::Method( method ) = callee {
+
+      // check arity [ the receiver doesn't count ]
+      if !method.native.arity().accepts( args.len() ) {
+        return Err( EvalError::Error( Error::from_token( paren,
+          format!( "Expected {} arguments to method call, but found {}.", method.native.arity(),
+            args.len() ) ) ) );
+      }
+
+      // the receiver goes ahead of the arguments
+      let mut args = args;
+      args.insert( 0, method.receiver.clone() );
+      let before = method.receiver.footprint();
+      let result = method.native.call( self, &args ).map_err( | e | self.native_error( e, paren ) )?;
+
+      // methods like push() grow their receiver in place
+      let grown = method.receiver.footprint().saturating_sub( before );
+      self.allocate( result.footprint() + grown, paren )?;
+      Ok( result )
+    } else {
+      Err( EvalError::Error( Error::from_token( paren,
+        format!( "Cannot call a {}.", callee.get_type_name() ) ) ) )
+    }
+  }
+  fn iterate( &self, iterable: Eval, keyword: &Token ) -> Result<Iteration, EvalError> {
+    match &iterable {
+      Eval::List( list ) => Ok( Iteration::Items( list.view().clone().into_iter() ) ),
+      Eval::StringLiteral( s ) => Ok( Iteration::Items(
+        s.chars().map( | c | Eval::StringLiteral( c.to_string() ) ).collect::<Vec<Eval>>().into_iter() ) ),
+      Eval::Map( map ) => Ok( Iteration::Items(
+        map.view().keys().map( | key | key.to_eval() ).collect::<Vec<Eval>>().into_iter() ) ),
+      Eval::Iterator( cursor ) => Ok( Iteration::Protocol( cursor.clone() ) ),
+      _ => Err( EvalError::Error( Error::from_token( keyword,
+        format!( "Cannot iterate over a value of type {}.", iterable.get_type_name() ) ) ) )
+    }
+  }
+  fn next_item( &mut self, iteration: &mut Iteration, keyword: &Token ) -> Result<Option<Eval>, EvalError> {
+    match iteration {
+      Iteration::Items( items ) => Ok( items.next() ),
+      Iteration::Protocol( cursor ) => {
+        if !self.call( cursor.has_next.clone(), Vec::new(), keyword )?.is_truthy() {
+          return Ok( None );
+        }
+        Ok( Some( self.call( cursor.next.clone(), Vec::new(), keyword )? ) )
+      }
+    }
+  }
+  /// Places a native's error at its call site, halting rather than raising if it ran out of budget.
+  fn native_error( &self, error: Error, paren: &Token ) -> EvalError {
+    let error = Error::at_call( error, paren );
+    if self.fuel.stopped() {
+      EvalError::Halt( error )
+    } else {
+      EvalError::Error( error )
+    }
+  }
+  /// Attaches the calls in progress to an error leaving a function, unless a deeper call already did.
+  fn traced( &self, error: EvalError ) -> EvalError {
+    match error {
+      EvalError::Error( e ) => EvalError::Error( e.with_trace( &self.calls ) ),
+      EvalError::Throw( value, e ) => EvalError::Throw( value, Box::new( e.with_trace( &self.calls ) ) ),
+      EvalError::Halt( e ) => EvalError::Halt( e.with_trace( &self.calls ) ),
+      other => other
+    }
+  }
+  fn run_catch( &mut self, catch: &stmt::Catch, exception: Eval ) -> Result<Eval, EvalError> {
+    self.envs = Env::new_with_enclosing( &self.envs );
+
+    // not charged, so a script can still catch running out of memory
+    self.envs.view_mut().create_symbol( &catch.name, &exception );
+    let result = stmt::Visitor::visit_block_stmt( self, &catch.body );
+    self.envs = Env::drop_enclosed( &self.envs );
+    result
+  }
   fn interpret_expr( &mut self, expr: &Expr ) -> Result<Eval, EvalError> {
     expr.accept( self )
   }
   fn interpret_stmt( &mut self, stmt: &Stmt ) -> Result<Eval, EvalError> {
+    if let Some( reason ) = self.fuel.burn( 1 ) {
+      let mut error = Error::from_msg( reason.into() );
+      error.line = self.line;
+      return Err( EvalError::Halt( error ) );
+    }
     stmt.accept( self )
   }
 }
 
+impl Default for Interpreter {
+  fn default() -> Interpreter {
+    Interpreter::new()
+  }
+}
+
 impl expr::Visitor<Result<Eval, EvalError>> for Interpreter {
   fn visit_assign_expr( &mut self, assign: &expr::Assign ) -> Result<Eval, EvalError> {
     let result = self.interpret_expr( &assign.rhs )?;
@@ -125,7 +552,20 @@ impl expr::Visitor<Result<Eval, EvalError>> for Interpreter {
             =>  match op_t {
 
                   // concatenation
-                  TokenType::Plus => Ok( Eval::StringLiteral( x.to_owned() + y ) ),
+                  TokenType::Plus => {
+                    self.allocate( x.len() + y.len(), op )?;
+                    Ok( Eval::StringLiteral( x.to_owned() + y ) )
+                  },
+
+                  // equality
+                  TokenType::EqualEqual => Ok( Eval::Bool( x == y ) ),
+                  TokenType::BangEqual  => Ok( Eval::Bool( x != y ) ),
+
+                  // comparison [ lexicographic, by character ]
+                  TokenType::Greater      => Ok( Eval::Bool( x > y ) ),
+                  TokenType::GreaterEqual => Ok( Eval::Bool( x >= y ) ),
+                  TokenType::Less         => Ok( Eval::Bool( x < y ) ),
+                  TokenType::LessEqual    => Ok( Eval::Bool( x <= y ) ),
 
                   // error
                   _ => Err( EvalError::Error( Error::from_token( op,
@@ -169,59 +609,72 @@ impl expr::Visitor<Result<Eval, EvalError>> for Interpreter {
   fn visit_call_expr( &mut self, call: &expr::Call ) -> Result<Eval, EvalError> {
 
     let callee = self.interpret_expr( &call.callee )?;
+    let mut args: Vec<Eval> = Vec::new();
+    for arg in &call.arguments {
+      args.push( self.interpret_expr( arg )? );
+    }
+    self.call( callee, args, &call.paren )
+  }
+  fn visit_get_expr( &mut self, get: &expr::Get ) -> Result<Eval, EvalError> {
+    let object = self.interpret_expr( &get.object )?;
+    match &object {
+      Eval::Module( module ) => match module.members.get( &get.name.lexeme ) {
+        Some( member ) => Ok( member.clone() ),
+        None => Err( EvalError::Error( Error::from_token( &get.name,
+          format!( "Module '{}' has no member '{}'.", module.name, get.name.lexeme ) ) ) )
+      },
+      Eval::StringLiteral( _ ) => match strings::string_method( &get.name.lexeme ) {
+        Some( native ) => Ok( Eval::Method( Rc::new( Method { receiver: object.clone(), native: Rc::new( native ) } ) ) ),
+        None => Err( EvalError::Error( Error::from_token( &get.name,
+          format!( "Strings have no method '{}'.", get.name.lexeme ) ) ) )
+      },
+      Eval::List( _ ) => match lists::list_method( &get.name.lexeme ) {
+        Some( native ) => Ok( Eval::Method( Rc::new( Method { receiver: object.clone(), native: Rc::new( native ) } ) ) ),
+        None => Err( EvalError::Error( Error::from_token( &get.name,
+          format!( "Lists have no method '{}'.", get.name.lexeme ) ) ) )
+      },
+      Eval::Map( map ) => match maps::map_method( &get.name.lexeme ) {
+        Some( native ) => Ok( Eval::Method( Rc::new( Method { receiver: object.clone(), native: Rc::new( native ) } ) ) ),
 
-    // if working correctly, callee will be an Eval::Fun
-    // from which we can invoke the function call.
-    if let Eval::Fun( f, closure ) = callee {
-  
-      // check arity
-      if f.params.len() != call.arguments.len() {
-        return Err( EvalError::Error( Error::from_token( &call.paren,
-          format!( "Expected {} arguments to function call, but found {}.", f.params.len(),
-            call.arguments.len() ) ) ) );
-      }
-
-      // prepare function scope
-      let mut args: Vec<Eval> = Vec::new();
-      for arg in &call.arguments {
-        args.push( self.interpret_expr( arg )? );
-      }
-      let callsite_envs = self.envs.clone();
-      self.envs = Env::new_with_enclosing( &closure );
-      for ( param, arg ) in std::iter::zip( f.params, args ) {
-        self.envs.view_mut().create_symbol( &param, &arg );
-      }
-
-      // execute
-      let mut exec_result = Eval::Nil;
-      for stmt in &f.body {
-        match self.interpret_stmt( stmt ) {
-          Ok( result ) => {
-            exec_result = result;
-          },
-          Err( EvalError::Return( retval ) ) => {
-            self.envs = Env::drop_enclosed( &self.envs );
-            self.envs = callsite_envs;
-            return Ok( retval );
-          },
-          actual_error => {
-            self.envs = Env::drop_enclosed( &self.envs );
-            self.envs = callsite_envs;
-            return actual_error;
-          }
+        // otherwise, m.name reads m[ "name" ], so Maps can act as objects
+        None => match map.view().get( &MapKey::String( get.name.lexeme.clone() ) ) {
+          Some( member ) => Ok( member.clone() ),
+          None => Err( EvalError::Error( Error::from_token( &get.name,
+            format!( "Maps have no method or key '{}'.", get.name.lexeme ) ) ) )
         }
-      };
-      self.envs = Env::drop_enclosed( &self.envs );
-      self.envs = callsite_envs;
-      Ok( exec_result )
-    } else {
-      Err( EvalError::Error( Error::from_token( &call.paren,
-        format!( "Cannot call a {}.", callee.get_type_name() ) ) ) )
+      },
+      _ => Err( EvalError::Error( Error::from_token( &get.name,
+        format!( "A value of type {} has no properties.", object.get_type_name() ) ) ) )
     }
   }
   fn visit_grouping_expr( &mut self, grouping: &expr::Grouping ) -> Result<Eval, EvalError> {
     self.interpret_expr( &grouping.expression )
   }
+  fn visit_index_expr( &mut self, index: &expr::Index ) -> Result<Eval, EvalError> {
+    let object = self.interpret_expr( &index.object )?;
+    let i = self.interpret_expr( &index.index )?;
+    match &object {
+      Eval::StringLiteral( s ) => strings::char_at( s, &i )
+        .map_err( | e | EvalError::Error( Error::at_call( e, &index.bracket ) ) ),
+      Eval::List( list ) => lists::get_index( list, &i )
+        .map_err( | e | EvalError::Error( Error::at_call( e, &index.bracket ) ) ),
+      Eval::Map( map ) => maps::get_key( map, &i )
+        .map_err( | e | EvalError::Error( Error::at_call( e, &index.bracket ) ) ),
+      _ => Err( EvalError::Error( Error::from_token( &index.bracket,
+        format!( "A value of type {} cannot be indexed.", object.get_type_name() ) ) ) )
+    }
+  }
+  fn visit_lambda_expr( &mut self, lambda: &expr::Lambda ) -> Result<Eval, EvalError> {
+    Ok( Eval::Fun( lambda.function.clone(), self.envs.clone() ) )
+  }
+  fn visit_list_expr( &mut self, list: &expr::List ) -> Result<Eval, EvalError> {
+    self.allocate( list.elements.len() * std::mem::size_of::<Eval>(), &list.bracket )?;
+    let mut elements: Vec<Eval> = Vec::new();
+    for element in &list.elements {
+      elements.push( self.interpret_expr( element )? );
+    }
+    Ok( Eval::List( RcMut::new( elements ) ) )
+  }
   fn visit_literal_expr( &mut self, literal: &expr::Literal ) -> Result<Eval, EvalError> {
     match literal.value.token_type {
       TokenType::Number => Ok( Eval::Number( literal.value.lexeme.parse::<f64>().unwrap() ) ),
@@ -232,9 +685,38 @@ impl expr::Visitor<Result<Eval, EvalError>> for Interpreter {
       _ => unreachable!( "Internal error: No other token types can be converted to Eval." )
     }
   }
+  fn visit_map_expr( &mut self, map: &expr::Map ) -> Result<Eval, EvalError> {
+    let bytes = map.entries.len() * ( std::mem::size_of::<MapKey>() + std::mem::size_of::<Eval>() );
+    self.allocate( bytes, &map.brace )?;
+    let mut entries: BTreeMap<MapKey, Eval> = BTreeMap::new();
+    for ( key, value ) in &map.entries {
+      let k = self.interpret_expr( key )?;
+      let k = MapKey::from_eval( &k ).map_err( | e | EvalError::Error( Error::at_call( e, &map.brace ) ) )?;
+      entries.insert( k, self.interpret_expr( value )? );
+    }
+    Ok( Eval::Map( RcMut::new( entries ) ) )
+  }
   // fn visit_logical_expr( &mut self, logical: &expr::Logical ) -> Result<Eval, EvalError> {
   //   todo!()
   // }
+  fn visit_set_index_expr( &mut self, set_index: &expr::SetIndex ) -> Result<Eval, EvalError> {
+    let object = self.interpret_expr( &set_index.object )?;
+    let i = self.interpret_expr( &set_index.index )?;
+    let value = self.interpret_expr( &set_index.value )?;
+    let before = object.footprint();
+    let result = match &object {
+      Eval::List( list ) => lists::set_index( list, &i, value )
+        .map_err( | e | EvalError::Error( Error::at_call( e, &set_index.bracket ) ) ),
+      Eval::Map( map ) => maps::set_key( map, &i, value )
+        .map_err( | e | EvalError::Error( Error::at_call( e, &set_index.bracket ) ) ),
+      _ => Err( EvalError::Error( Error::from_token( &set_index.bracket,
+        format!( "A value of type {} does not support index assignment.", object.get_type_name() ) ) ) )
+    }?;
+
+    // a new key grows a map
+    self.allocate( object.footprint().saturating_sub( before ), &set_index.bracket )?;
+    Ok( result )
+  }
   fn visit_unary_expr( &mut self, unary: &expr::Unary ) -> Result<Eval, EvalError> {
     let right = self.interpret_expr( &unary.right )?;
     match unary.operator.token_type {
@@ -257,6 +739,7 @@ impl expr::Visitor<Result<Eval, EvalError>> for Interpreter {
 
 impl stmt::Visitor<Result<Eval, EvalError>> for Interpreter {
   fn visit_block_stmt( &mut self, block: &stmt::Block ) -> Result<Eval, EvalError> {
+    self.line = block.line;
     self.envs = Env::new_with_enclosing( &self.envs );
     let mut result = Eval::Nil;
     for stmt in &block.statements {
@@ -273,12 +756,41 @@ impl stmt::Visitor<Result<Eval, EvalError>> for Interpreter {
     self.envs = Env::drop_enclosed( &self.envs );
     Ok( result )
   }
+  fn visit_break_stmt( &mut self, _break: &stmt::Break ) -> Result<Eval, EvalError> {
+    Err( EvalError::Break )
+  }
+  fn visit_continue_stmt( &mut self, _continue: &stmt::Continue ) -> Result<Eval, EvalError> {
+    Err( EvalError::Continue )
+  }
   fn visit_expression_stmt( &mut self, expression: &stmt::Expression ) -> Result<Eval,EvalError> {
     self.interpret_expr( &expression.expression )
   }
+  fn visit_for_in_stmt( &mut self, for_in: &stmt::ForIn ) -> Result<Eval, EvalError> {
+    let iterable = self.interpret_expr( &for_in.iterable )?;
+    let mut iteration = self.iterate( iterable, &for_in.keyword )?;
+    let mut result = Eval::Nil;
+    while let Some( item ) = self.next_item( &mut iteration, &for_in.keyword )? {
+      self.line = for_in.keyword.line;
+
+      // each pass gets a fresh loop variable, so closures capture the value of that pass
+      self.envs = Env::new_with_enclosing( &self.envs );
+      let exec_result = match self.bind( &for_in.name, &item ) {
+        Ok( () ) => self.interpret_stmt( &for_in.body ),
+        Err( e ) => Err( e )
+      };
+      self.envs = Env::drop_enclosed( &self.envs );
+      match exec_result {
+        Ok( eval ) => result = eval,
+        Err( EvalError::Break ) => break,
+        Err( EvalError::Continue ) => continue,
+        Err( e ) => return Err( e )
+      }
+    }
+    Ok( result )
+  }
   fn visit_function_stmt( &mut self, function: &stmt::Function ) -> Result<Eval, EvalError> {
     let result = Eval::Fun( function.clone(), self.envs.clone() );
-    self.envs.view_mut().create_symbol( &function.name, &result );
+    self.bind( &function.name, &result )?;
     Ok( result )
   }
   fn visit_if_stmt( &mut self, if_: &stmt::If ) -> Result<Eval, EvalError> {
@@ -292,7 +804,11 @@ impl stmt::Visitor<Result<Eval, EvalError>> for Interpreter {
   }
   fn visit_print_stmt( &mut self, print: &stmt::Print ) -> Result<Eval, EvalError> {
     let result = self.interpret_expr( &print.expression )?;
-    println!( "{}", result );
+    if let Err( e ) = writeln!( self.out, "{}", result ) {
+      let mut error = Error::from_msg( format!( "Error writing output: {}", e ) );
+      error.line = self.line;
+      return Err( EvalError::Error( error ) );
+    }
     Ok( result )
   }
   fn visit_return_stmt( &mut self, return_: &stmt::Return ) -> Result<Eval, EvalError> {
@@ -302,21 +818,84 @@ impl stmt::Visitor<Result<Eval, EvalError>> for Interpreter {
       Err( EvalError::Return( Eval::Nil ) )
     }
   }
+  fn visit_throw_stmt( &mut self, throw: &stmt::Throw ) -> Result<Eval, EvalError> {
+    let value = self.interpret_expr( &throw.value )?;
+    let uncaught = uncaught_error( &value, throw.keyword.line );
+    Err( EvalError::Throw( value, Box::new( uncaught ) ) )
+  }
+  fn visit_try_stmt( &mut self, try_: &stmt::Try ) -> Result<Eval, EvalError> {
+    let mut result = self.visit_block_stmt( &try_.body );
+
+    // runtime errors are caught as values too
+    if let Some( catch ) = &try_.catch {
+      result = match result {
+        Err( EvalError::Error( e ) ) => self.run_catch( catch, error_value( &e ) ),
+        Err( EvalError::Throw( value, _ ) ) => self.run_catch( catch, value ),
+        other => other
+      };
+    }
+
+    // runs however the try and catch blocks finished, unless it has its own exit
+    if let Some( finally ) = &try_.finally {
+      if !matches!( result, Err( EvalError::Halt( _ ) | EvalError::Exit ) ) {
+        self.visit_block_stmt( finally )?;
+      }
+    }
+    result
+  }
   fn visit_var_stmt( &mut self, var: &stmt::Var ) -> Result<Eval, EvalError> {
     let value = match var.init.as_ref() {
       Some( expr ) => self.interpret_expr( expr )?,
       None => Eval::Nil
     };
-    self.envs.view_mut().create_symbol( &var.name, &value );
+    self.bind( &var.name, &value )?;
     Ok( value )
   }
   fn visit_while_stmt( &mut self, while_: &stmt::While ) -> Result<Eval, EvalError> {
     let mut result = Eval::Nil;
     loop {
+
+      // so running out of budget in a loop without a block reports the loop's line
+      self.line = while_.keyword.line;
       if !self.interpret_expr( &while_.condition )?.is_truthy() {
         return Ok( result );
       }
-      result = self.interpret_stmt( &while_.body )?;
+      match self.interpret_stmt( &while_.body ) {
+        Ok( eval ) => result = eval,
+        Err( EvalError::Break ) => return Ok( result ),
+        Err( EvalError::Continue ) => {},
+        Err( e ) => return Err( e )
+      }
+      if let Some( increment ) = &while_.increment {
+        self.interpret_expr( increment )?;
+      }
     }
   }
-}
\ No newline at end of file
+}
+
+/// Counts a String `value` where it's found, and queues anything that holds further values.
+fn reach( value: &Eval, bytes: &mut usize, envs: &mut Vec<RcMut<Env>>, values: &mut Vec<Eval> ) {
+  match value {
+    Eval::StringLiteral( s ) => *bytes += s.len(),
+    Eval::Fun( _, closure ) => envs.push( closure.clone() ),
+    Eval::List( _ ) | Eval::Map( _ ) | Eval::Method( _ ) | Eval::Module( _ ) | Eval::Iterator( _ ) => values.push( value.clone() ),
+    _ => {}
+  }
+}
+
+/// The bytes a binding of `name` takes up in an environment.
+fn binding_size( name: &Token ) -> usize {
+  name.lexeme.len() + std::mem::size_of::<Eval>()
+}
+
+/// The value a runtime error is caught as: a Map of its `message` and `line`.
+fn error_value( error: &Error ) -> Eval {
+  let mut map: BTreeMap<MapKey, Eval> = BTreeMap::new();
+  map.insert( MapKey::String( "message".into() ), Eval::StringLiteral( error.msg.clone() ) );
+  map.insert( MapKey::String( "line".into() ), Eval::Number( error.line as f64 ) );
+  Eval::Map( RcMut::new( map ) )
+}
+
+fn uncaught_error( exception: &Eval, line: u32 ) -> Error {
+  Error { line, loc: " at 'throw'".into(), msg: format!( "Uncaught exception: {}", exception.repr() ), trace: Vec::new() }
+}
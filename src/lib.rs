@@ -1,4 +1,4 @@
-use std::{fs, io::{self, Write, BufRead}};
+use std::{fs, io::{self, Write}, thread};
 
 mod util;
 mod token;
@@ -12,14 +12,40 @@ mod env;
 mod resolver;
 mod eval;
 mod byte_code;
+mod ast_printer;
+mod repl;
+mod engine;
+mod native;
+mod prelude;
+mod math;
+mod strings;
+mod lists;
+mod maps;
+mod test_runner;
+mod formatter;
+mod cst;
+
+pub use eval::Eval as Value;
+pub use error::{Diagnostics, Error};
+pub use engine::Engine;
+pub use interpreter::{Interpreter, DEFAULT_MAX_DEPTH, ENGINE_MAX_DEPTH, HOST_STACK_SIZE, MAX_DEPTH, STACK_SIZE};
+pub use native::{Arity, Native, NativeFn};
+pub use cst::{NodeKind, SyntaxElement, SyntaxNode, SyntaxToken, Trivia, TriviaKind};
+pub use token::TokenType;
 
 
 use eval::Eval;
 use scanner::Scanner;
 use parser::Parser;
 use resolver::Resolver;
-use interpreter::Interpreter;
-use byte_code::{Compiler, Vm};
+use stmt::Stmt;
+use error::report_warnings;
+use byte_code::{ByteCode, Compiler, Vm};
+use repl::Repl;
+use formatter::Formatter;
+use ast_printer::{AstPrinter, JsonPrinter};
+use util::RcMut;
+use token::Token;
 // use crate::interpreter::Interpreter;
 
 // pub fn new() -> Interpreter {
@@ -32,89 +58,395 @@ use byte_code::{Compiler, Vm};
 // }
 
 
-pub fn run_file( path: &str ) {
-  let file = fs::read_to_string( path );
-  match file {
-    Ok( src ) => {
-      let ( eval, had_error ) = run( src );
-      println!( "\n\n----------------------\nExecution finished with return value {}. ", eval );
-      if had_error {
-        print!( "Runtime errors were detected." );
+/// How a command finished, mapped onto the conventional exit codes of `sysexits.h`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Status {
+  Success,
+  CompileError,
+  RuntimeError,
+  IoError,
+
+  // some script under `rlox test` didn't behave as its comments expect
+  TestFailure,
+
+  // some file under `rlox fmt --check` isn't formatted
+  Unformatted,
+
+  // the script called `exit( code )`
+  Exit( i32 )
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Backend {
+  TreeWalk,
+
+  // runs the whole language except for-in loops and `iterator()`, which are tree-walk only for now
+  ByteCode
+}
+
+/// The resources a script run may use before it is stopped.
+#[derive(Clone, Copy)]
+pub struct Limits {
+  pub max_depth: usize,
+
+  // statements for the tree-walk interpreter, instructions for the VM [ None for no limit ]
+  pub budget: Option<u64>,
+
+  // bytes held in strings, collections and bindings [ None for the built-in ceiling ]
+  pub max_memory: Option<usize>
+}
+
+/// What a script run left behind, for the caller to report.
+struct Outcome {
+  value: Eval,
+  errors: Vec<Error>,
+  status: Status,
+
+  // the backend the script ran on, kept so its functions can be called afterwards
+  session: Option<Session>
+}
+
+/// A backend that has run a script, along with the globals it left behind.
+enum Session {
+  TreeWalk( Box<Interpreter> ),
+  ByteCode( Box<Vm> )
+}
+
+impl Status {
+  pub fn exit_code( &self ) -> u8 {
+    match self {
+      Status::Success => 0,
+      Status::CompileError => 65,
+      Status::RuntimeError => 70,
+      Status::IoError => 66,
+      Status::TestFailure => 1,
+      Status::Unformatted => 1,
+      Status::Exit( code ) => *code as u8
+    }
+  }
+}
+
+impl Default for Limits {
+  fn default() -> Limits {
+    Limits {
+      max_depth: DEFAULT_MAX_DEPTH,
+      budget: None,
+      max_memory: None
+    }
+  }
+}
+
+impl Outcome {
+  fn new( value: Eval, errors: Vec<Error>, status: Status ) -> Outcome {
+    Outcome {
+      value,
+      errors,
+      status,
+      session: None
+    }
+  }
+}
+
+impl Session {
+  /// Calls the script's function `name` with no arguments, returning the errors that stopped it.
+  fn call( &mut self, name: &str ) -> Vec<Error> {
+    let token = Token { token_type: TokenType::Identifier, lexeme: name.into(), line: 0 };
+    let mut errors = match self {
+      Session::TreeWalk( interpreter ) => match interpreter.global( name ) {
+        Some( callee ) => {
+          interpreter.call_function( callee, Vec::new(), &token );
+          interpreter.take_errors()
+        },
+        None => vec![ Error::from_token( &token, "Undeclared symbol.".into() ) ]
+      },
+      Session::ByteCode( vm ) => match vm.global( name ) {
+        Some( callee ) => {
+          vm.call_function( callee, Vec::new() );
+          vm.take_errors()
+        },
+        None => vec![ Error::from_token( &token, "Undeclared symbol.".into() ) ]
+      }
+    };
+
+    // the call came from here, not from a line of the script
+    for error in &mut errors {
+      if !error.trace.is_empty() {
+        error.trace.remove( 0 );
+      }
+    }
+    errors
+  }
+}
+
+/// Runs a script; `trace` dumps each VM instruction and the stack after it to stderr.
+pub fn run_file( path: &str, args: &[String], backend: Backend, limits: Limits, verbose: bool, trace: bool ) -> Status {
+  let src = match read_source( path ) {
+    Ok( src ) => src,
+    Err( status ) => return status
+  };
+  let outcome = execute( src, args, backend, limits, trace, true, Box::new( io::stdout() ) );
+  Diagnostics::new( outcome.errors ).report();
+  if verbose && outcome.status != Status::CompileError {
+    eprintln!( "\n\n----------------------\nExecution finished with return value {}. ", outcome.value );
+    if outcome.status == Status::RuntimeError {
+      eprintln!( "Runtime errors were detected." );
+    }
+    else {
+      eprintln!( "No runtime errors detected." );
+    }
+  }
+  outcome.status
+}
+
+/// Runs every script under `dir` against the expectations written into its comments.
+pub fn test_dir( dir: &str, backend: Backend ) -> Status {
+  test_runner::run_tests( dir, backend )
+}
+
+/// Rewrites each file in the canonical layout, or with `check` only lists those that would change.
+pub fn format_files( paths: &[String], check: bool ) -> Status {
+  let mut status = Status::Success;
+  for path in paths {
+    let src = match read_source( path ) {
+      Ok( src ) => src,
+      Err( status ) => return status
+    };
+    let formatted = match format_source( src.clone() ) {
+      Ok( formatted ) => formatted,
+      Err( diagnostics ) => {
+        diagnostics.report();
+        status = Status::CompileError;
+        continue;
       }
-      else {
-        print!( "No runtime errors detected." );
+    };
+    if formatted == src {
+      continue;
+    }
+    if check {
+      println!( "{}", path );
+      if status == Status::Success {
+        status = Status::Unformatted;
+      }
+    } else if let Err( e ) = fs::write( path, formatted ) {
+      eprintln!( "Error writing file '{}': {}", path, e );
+      return Status::IoError;
+    }
+  }
+  status
+}
+
+pub fn check_file( path: &str ) -> Status {
+  let src = match read_source( path ) {
+    Ok( src ) => src,
+    Err( status ) => return status
+  };
+  match front_end( src, global_names( &script_interpreter( &[] ) ), true ) {
+    Ok( _ ) => Status::Success,
+    Err( diagnostics ) => {
+      diagnostics.report();
+      Status::CompileError
+    }
+  }
+}
+
+/// Prints the resolved syntax tree of a script, as S-expressions or with `json` as JSON.
+pub fn print_ast( path: &str, json: bool ) -> Status {
+  let src = match read_source( path ) {
+    Ok( src ) => src,
+    Err( status ) => return status
+  };
+  match front_end( src, global_names( &script_interpreter( &[] ) ), false ) {
+    Ok( stmts ) => {
+      if json {
+        print!( "{}", JsonPrinter::new().print_stmts( &stmts ) );
+      } else {
+        print!( "{}", AstPrinter::new().print_stmts( &stmts ) );
       }
+      Status::Success
     },
-    Err( e ) => eprintln!( "Error reading file: {}", e )
-  }
-} 
-
-pub fn run_prompt() {
-  let stdin = io::stdin();   
-  loop {
-    print!( "\n> " );
-    let _ = io::stdout().flush();
-    let mut input = String::new();
-    match stdin.lock().read_line( &mut input ) {
-      Ok( _ ) => {
-        let ( eval, had_error ) = run( input );
-        if had_error {
-          println!( "\nErr( {} )", eval );
-        }
-        else {
-          println!( "\nOk( {} )", eval );
-        }        
-      },
-      Err( e ) => eprintln!( "Error reading stdin: {}", e )
+    Err( diagnostics ) => {
+      diagnostics.report();
+      Status::CompileError
     }
   }
 }
 
-fn run( src: String ) -> ( Eval, bool ) {
+pub fn compile_file( path: &str ) -> Status {
+  match compile_source( path ) {
+    Ok( _ ) => Status::Success,
+    Err( status ) => status
+  }
+}
+
+pub fn disasm_file( path: &str ) -> Status {
+  match compile_source( path ) {
+    Ok( byte_code ) => {
+      print!( "{}", byte_code.disassemble() );
+      Status::Success
+    },
+    Err( status ) => status
+  }
+}
+
+/// Runs `f` on a thread with a `STACK_SIZE` stack, which `--max-depth` calls are sized against,
+/// rather than on whatever stack the caller happens to have.
+pub fn with_stack<T: Send + 'static>( f: impl FnOnce() -> T + Send + 'static ) -> T {
+  let worker = thread::Builder::new().stack_size( STACK_SIZE ).spawn( f )
+    .expect( "Internal error: Couldn't start the interpreter thread." );
+  match worker.join() {
+    Ok( result ) => result,
+    Err( panic ) => std::panic::resume_unwind( panic )
+  }
+}
+
+pub fn run_prompt() -> Status {
+  match Repl::new().run() {
+    Some( code ) => Status::Exit( code ),
+    None => Status::Success
+  }
+}
+
+fn read_source( path: &str ) -> Result<String, Status> {
+  fs::read_to_string( path ).map_err( | e | {
+    eprintln!( "Error reading file '{}': {}", path, e );
+    Status::IoError
+  } )
+}
+
+fn compile_source( path: &str ) -> Result<ByteCode, Status> {
+  let src = read_source( path )?;
+  let stmts = front_end( src, global_names( &script_interpreter( &[] ) ), true ).map_err( | diagnostics | {
+    diagnostics.report();
+    Status::CompileError
+  } )?;
+  let mut codegen = Compiler::new();
+  let ( byte_code, had_codegen_error ) = codegen.compile( &stmts );
+  if had_codegen_error {
+    Diagnostics::new( codegen.take_errors() ).report();
+    return Err( Status::CompileError );
+  }
+  Ok( byte_code )
+}
+
+// script arguments are exposed as the globals `argc` and `args` [ a list of strings ]
+fn script_interpreter( args: &[String] ) -> Interpreter {
+  let mut interpreter = Interpreter::new();
+  interpreter.define_global( "argc", Eval::Number( args.len() as f64 ) );
+  let args = args.iter().map( | arg | Eval::StringLiteral( arg.clone() ) ).collect();
+  interpreter.define_global( "args", Eval::List( RcMut::new( args ) ) );
+  interpreter
+}
+
+fn global_names( interpreter: &Interpreter ) -> Vec<String> {
+  interpreter.globals().into_iter().map( | ( name, _ ) | name ).collect()
+}
+
+/// Runs `src` start to finish on `backend`, printing to `out`, and collects the errors it stopped on.
+fn execute( src: String, args: &[String], backend: Backend, limits: Limits, trace: bool, warn: bool,
+  out: Box<dyn Write> ) -> Outcome {
+  let mut interpreter = script_interpreter( args );
+  let stmts = match front_end( src, global_names( &interpreter ), warn ) {
+    Ok( stmts ) => stmts,
+    Err( diagnostics ) => return Outcome::new( Eval::Nil, diagnostics.errors, Status::CompileError )
+  };
+  let ( value, had_error, errors, exit_code, session ) = match backend {
+    Backend::TreeWalk => {
+      interpreter.set_max_depth( limits.max_depth );
+      interpreter.set_budget( limits.budget );
+      interpreter.set_memory_limit( limits.max_memory );
+      interpreter.set_output( out );
+      let ( value, had_error ) = interpreter.interpret( &stmts );
+      let ( errors, exit_code ) = ( interpreter.take_errors(), interpreter.exit_code() );
+      ( value, had_error, errors, exit_code, Session::TreeWalk( Box::new( interpreter ) ) )
+    },
+    Backend::ByteCode => {
+      let mut codegen = Compiler::new();
+      let ( byte_code, had_codegen_error ) = codegen.compile( &stmts );
+      if had_codegen_error {
+        return Outcome::new( Eval::Nil, codegen.take_errors(), Status::CompileError );
+      }
+      let mut vm = Vm::new( byte_code );
+      vm.define_global( "argc", byte_code::Value::Number( args.len() as f64 ) );
+      let args = args.iter().map( | arg | byte_code::Value::String( arg.clone() ) ).collect();
+      vm.define_global( "args", byte_code::Value::List( RcMut::new( args ) ) );
+      vm.set_max_depth( limits.max_depth );
+      vm.set_budget( limits.budget );
+      vm.set_memory_limit( limits.max_memory );
+      vm.set_output( out );
+      vm.set_trace( trace );
+      let ( value, had_error ) = vm.exec();
+      let ( errors, exit_code ) = ( vm.take_errors(), vm.exit_code() );
+      ( value, had_error, errors, exit_code, Session::ByteCode( Box::new( vm ) ) )
+    }
+  };
+  let status = match exit_code {
+    Some( code ) => Status::Exit( code ),
+    None if had_error => Status::RuntimeError,
+    None => Status::Success
+  };
+  Outcome {
+    session: Some( session ),
+    ..Outcome::new( value, errors, status )
+  }
+}
+
+/// Parses `src` into a concrete syntax tree, which keeps every character of it:
+/// printing the tree gives `src` back unchanged.
+///
+/// Source with errors still gets a tree, alongside the errors: the nodes a parse error
+/// left open are closed where it stopped, and the text the scanner couldn't make
+/// tokens of is kept as trivia.
+pub fn syntax_tree( src: &str ) -> ( SyntaxNode, Vec<Error> ) {
+  let mut scanner = Scanner::new();
+  let ( tokens, _ ) = scanner.scan( src.into() );
+  let mut errors = scanner.take_errors();
+  let syntax_tokens = scanner.syntax_tokens( &tokens );
+  let mut parser = Parser::new();
+  parser.parse( tokens );
+  errors.extend( parser.take_errors() );
+  ( cst::build( &parser.take_events(), syntax_tokens ), errors )
+}
+
+// scans and parses `src` [ no need to resolve ] and prints it back out, comments and all
+fn format_source( src: String ) -> Result<String, Diagnostics> {
+  let mut scanner = Scanner::new();
+  let ( tokens, had_scan_error ) = scanner.scan( src );
+  if had_scan_error {
+    return Err( Diagnostics::new( scanner.take_errors() ) );
+  }
+  let mut parser = Parser::new();
+  let ( stmts, had_parse_error ) = parser.parse( tokens );
+  if had_parse_error {
+    return Err( Diagnostics::new( parser.take_errors() ) );
+  }
+  Ok( Formatter::new( scanner.take_comments() ).format( &stmts ) )
+}
+
+fn front_end( src: String, globals: Vec<String>, warn: bool ) -> Result<Vec<Stmt>, Diagnostics> {
 
   // scanner / lexer
   let mut scanner = Scanner::new();
   let ( tokens, had_scan_error ) = scanner.scan( src );
   if had_scan_error {
-    return ( Eval::Nil, true );
+    return Err( Diagnostics::new( scanner.take_errors() ) );
   }
 
   // parser
   let mut parser = Parser::new();
   let ( mut stmts, had_parse_error ) = parser.parse( tokens );
   if had_parse_error {
-    return ( Eval::Nil, true );
+    return Err( Diagnostics::new( parser.take_errors() ) );
   }
 
   // resolver
-  let mut resolver = Resolver::new();
+  let mut resolver = Resolver::with_globals( globals );
   let had_resolve_error = resolver.resolve( &mut stmts );
-  if had_resolve_error {
-    return ( Eval::Nil, true );
+  if warn {
+    report_warnings( &resolver.take_warnings() );
   }
-
-  let mut codegen = Compiler::new();
-  let ( byte_code, had_codegen_error ) = codegen.compile( &stmts );
-  if had_codegen_error {
-    return ( Eval::Nil, true );
+  if had_resolve_error {
+    return Err( Diagnostics::new( resolver.take_errors() ) );
   }
 
-  let mut vm = Vm::new( byte_code );
-  vm.exec()
-
-  // interpreter
-  // let mut interpreter = Interpreter::new();
-  // interpreter.interpret( &stmts )
-
-  
-
-  
-
-//   // module
-//   let module = Module::new( ast, scope_tree );
-//   module.exec()
-
-//   // executor
-//   // let mut executor = Executor::new( &mut self.str_lookup );
-//   // executor.exec( decls )
-}
\ No newline at end of file
+  Ok( stmts )
+}
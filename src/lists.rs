@@ -0,0 +1,219 @@
+////////////////////////////////
+// private module rlox::lists //
+////////////////////////////////
+
+
+/////////
+// use //
+/////////
+
+use crate::{byte_code::{NativeFunction, Value, Vm}, error::Error, eval::Eval, interpreter::Interpreter,
+  native::{Arity, Native}, util::RcMut};
+
+
+/////////////////////
+// implementations //
+/////////////////////
+
+/// Looks up the method `name` on Lists.
+pub fn list_method( name: &str ) -> Option<Native> {
+  let method = match name {
+    "len" => Native::new( name, Arity::Fixed( 0 ), len ),
+    "push" => Native::new( name, Arity::Fixed( 1 ), push ),
+    "pop" => Native::new( name, Arity::Fixed( 0 ), pop ),
+    "insert" => Native::new( name, Arity::Fixed( 2 ), insert ),
+    "remove" => Native::new( name, Arity::Fixed( 1 ), remove ),
+    "slice" => Native::new( name, Arity::Fixed( 2 ), slice ),
+    _ => return None
+  };
+  Some( method )
+}
+
+/// Evaluates `list[ index ]`.
+pub fn get_index( list: &RcMut<Vec<Eval>>, index: &Eval ) -> Result<Eval, Error> {
+  let items = list.view();
+  let i = position( "List index", number( index ), index.repr(), items.len(), false )?;
+  Ok( items[ i ].clone() )
+}
+
+/// Evaluates `list[ index ] = value`.
+pub fn set_index( list: &RcMut<Vec<Eval>>, index: &Eval, value: Eval ) -> Result<Eval, Error> {
+  let mut list = list.clone();
+  let mut items = list.view_mut();
+  let i = position( "List index", number( index ), index.repr(), items.len(), false )?;
+  items[ i ] = value.clone();
+  Ok( value )
+}
+
+/// The byte code counterpart of `list_method`.
+pub fn vm_list_method( name: &str ) -> Option<NativeFunction> {
+  let method = match name {
+    "len" => NativeFunction::new( name, Arity::Fixed( 0 ), vm_len ),
+    "push" => NativeFunction::new( name, Arity::Fixed( 1 ), vm_push ),
+    "pop" => NativeFunction::new( name, Arity::Fixed( 0 ), vm_pop ),
+    "insert" => NativeFunction::new( name, Arity::Fixed( 2 ), vm_insert ),
+    "remove" => NativeFunction::new( name, Arity::Fixed( 1 ), vm_remove ),
+    "slice" => NativeFunction::new( name, Arity::Fixed( 2 ), vm_slice ),
+    _ => return None
+  };
+  Some( method )
+}
+
+/// The byte code counterpart of `get_index`.
+pub fn vm_get_index( list: &RcMut<Vec<Value>>, index: &Value ) -> Result<Value, Error> {
+  let items = list.view();
+  let i = position( "List index", vm_number( index ), index.repr(), items.len(), false )?;
+  Ok( items[ i ].clone() )
+}
+
+/// The byte code counterpart of `set_index`.
+pub fn vm_set_index( list: &RcMut<Vec<Value>>, index: &Value, value: Value ) -> Result<Value, Error> {
+  let mut list = list.clone();
+  let mut items = list.view_mut();
+  let i = position( "List index", vm_number( index ), index.repr(), items.len(), false )?;
+  items[ i ] = value.clone();
+  Ok( value )
+}
+
+
+/////////////////////////////////
+// shared by both the backends //
+/////////////////////////////////
+
+/// Checks that `index` is a whole number within `0..len`, or `0..=len` when `inclusive`.
+///
+/// The check happens before the conversion to `usize`, so an error quotes the number as written.
+fn position( what: &str, index: Option<f64>, found: String, len: usize, inclusive: bool ) -> Result<usize, Error> {
+  let x = match index {
+    Some( x ) if x.fract() == 0.0 && x >= 0.0 => x,
+    _ => return Err( Error::from_msg( format!( "{} must be a whole, non-negative Number, but found {}.", what, found ) ) )
+  };
+  if x > len as f64 || ( x == len as f64 && !inclusive ) {
+    return Err( Error::from_msg( format!( "{} {} is out of range for a List of length {}.", what, found, len ) ) );
+  }
+  Ok( x as usize )
+}
+
+fn empty_error( fun: &str ) -> Error {
+  Error::from_msg( format!( "{}() cannot be called on an empty List.", fun ) )
+}
+
+fn range_error( start: usize, end: usize ) -> Error {
+  Error::from_msg( format!( "slice() range {}..{} runs backwards.", start, end ) )
+}
+
+
+///////////////////////////
+// tree-walk interpreter //
+///////////////////////////
+
+fn receiver( args: &[Eval] ) -> RcMut<Vec<Eval>> {
+  match &args[ 0 ] {
+    Eval::List( list ) => list.clone(),
+    _ => unreachable!( "Internal error: List methods are only bound to Lists." )
+  }
+}
+
+fn number( arg: &Eval ) -> Option<f64> {
+  match arg {
+    Eval::Number( x ) => Some( *x ),
+    _ => None
+  }
+}
+
+fn len( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  Ok( Eval::Number( receiver( args ).view().len() as f64 ) )
+}
+
+fn push( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  receiver( args ).view_mut().push( args[ 1 ].clone() );
+  Ok( Eval::Nil )
+}
+
+fn pop( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  receiver( args ).view_mut().pop().ok_or_else( || empty_error( "pop" ) )
+}
+
+fn insert( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  let mut list = receiver( args );
+  let mut items = list.view_mut();
+  let i = position( "insert() index", number( &args[ 1 ] ), args[ 1 ].repr(), items.len(), true )?;
+  items.insert( i, args[ 2 ].clone() );
+  Ok( Eval::Nil )
+}
+
+fn remove( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  let mut list = receiver( args );
+  let mut items = list.view_mut();
+  let i = position( "remove() index", number( &args[ 1 ] ), args[ 1 ].repr(), items.len(), false )?;
+  Ok( items.remove( i ) )
+}
+
+fn slice( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  let list = receiver( args );
+  let items = list.view();
+  let start = position( "slice() start", number( &args[ 1 ] ), args[ 1 ].repr(), items.len(), true )?;
+  let end = position( "slice() end", number( &args[ 2 ] ), args[ 2 ].repr(), items.len(), true )?;
+  if start > end {
+    return Err( range_error( start, end ) );
+  }
+  Ok( Eval::List( RcMut::new( items[ start..end ].to_vec() ) ) )
+}
+
+
+///////////////
+// byte code //
+///////////////
+
+fn vm_receiver( args: &[Value] ) -> RcMut<Vec<Value>> {
+  match &args[ 0 ] {
+    Value::List( list ) => list.clone(),
+    _ => unreachable!( "Internal error: List methods are only bound to Lists." )
+  }
+}
+
+fn vm_number( arg: &Value ) -> Option<f64> {
+  match arg {
+    Value::Number( x ) => Some( *x ),
+    _ => None
+  }
+}
+
+fn vm_len( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  Ok( Value::Number( vm_receiver( args ).view().len() as f64 ) )
+}
+
+fn vm_push( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_receiver( args ).view_mut().push( args[ 1 ].clone() );
+  Ok( Value::Nil )
+}
+
+fn vm_pop( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_receiver( args ).view_mut().pop().ok_or_else( || empty_error( "pop" ) )
+}
+
+fn vm_insert( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  let mut list = vm_receiver( args );
+  let mut items = list.view_mut();
+  let i = position( "insert() index", vm_number( &args[ 1 ] ), args[ 1 ].repr(), items.len(), true )?;
+  items.insert( i, args[ 2 ].clone() );
+  Ok( Value::Nil )
+}
+
+fn vm_remove( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  let mut list = vm_receiver( args );
+  let mut items = list.view_mut();
+  let i = position( "remove() index", vm_number( &args[ 1 ] ), args[ 1 ].repr(), items.len(), false )?;
+  Ok( items.remove( i ) )
+}
+
+fn vm_slice( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  let list = vm_receiver( args );
+  let items = list.view();
+  let start = position( "slice() start", vm_number( &args[ 1 ] ), args[ 1 ].repr(), items.len(), true )?;
+  let end = position( "slice() end", vm_number( &args[ 2 ] ), args[ 2 ].repr(), items.len(), true )?;
+  if start > end {
+    return Err( range_error( start, end ) );
+  }
+  Ok( Value::List( RcMut::new( items[ start..end ].to_vec() ) ) )
+}
@@ -1,15 +1,114 @@
-use rlox::{run_file, run_prompt};
+use std::process::ExitCode;
 
-fn main() {
-  let args: Vec<String> = std::env::args().collect();
+use clap::{Parser, Subcommand};
 
-  if args.len() > 2 {
-    eprintln!( "Usage: {} [optional:script]", args.get( 0 ).unwrap() );
-  }
-  if args.len() == 2 {
-    run_file( args.get( 1 ).unwrap() );
+use rlox::{check_file, compile_file, disasm_file, format_files, print_ast, run_file, run_prompt, test_dir, with_stack, Backend,
+  Limits, DEFAULT_MAX_DEPTH, MAX_DEPTH};
+
+#[derive(Parser)]
+#[command(name = "rlox", version, about = "A Lox interpreter, after Crafting Interpreters.")]
+struct Cli {
+  #[command(subcommand)]
+  command: Option<Command>
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// Run a script
+  Run {
+    script: String,
+    /// Arguments passed on to the script
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<String>,
+    /// Execute on the byte code VM instead of the tree-walk interpreter
+    #[arg(long)]
+    vm: bool,
+    /// The deepest calls may nest before the script fails with a stack overflow
+    #[arg(long, value_name = "CALLS", default_value_t = DEFAULT_MAX_DEPTH, value_parser = parse_max_depth)]
+    max_depth: usize,
+    /// Stop the script after this many steps [ statements, or instructions with --vm ]
+    #[arg(long, value_name = "STEPS")]
+    budget: Option<u64>,
+    /// Stop the script with an error once it holds more than this many bytes
+    #[arg(long, value_name = "BYTES")]
+    max_memory: Option<usize>,
+    /// Report the final value and error status once execution finishes
+    #[arg(short, long)]
+    verbose: bool,
+    /// Dump each VM instruction and the stack after it to stderr
+    #[arg(long, hide = true)]
+    trace: bool
+  },
+  /// Start an interactive prompt (the default)
+  Repl,
+  /// Scan, parse and resolve a script without running it
+  Check {
+    script: String
+  },
+  /// Print the syntax tree of a script once names are resolved
+  Ast {
+    script: String,
+    /// Print JSON, with each variable's resolved scope depth, instead of S-expressions
+    #[arg(long)]
+    json: bool
+  },
+  /// Compile a script to byte code, reporting any errors
+  Compile {
+    script: String
+  },
+  /// Print the byte code listing for a script
+  Disasm {
+    script: String
+  },
+  /// Run the scripts under a directory, checking them against their `// expect` comments
+  Test {
+    dir: String,
+    /// Execute on the byte code VM instead of the tree-walk interpreter
+    #[arg(long)]
+    vm: bool
+  },
+  /// Rewrite scripts in the canonical layout, keeping their comments
+  Fmt {
+    #[arg(required = true)]
+    scripts: Vec<String>,
+    /// List the scripts that aren't formatted instead of rewriting them
+    #[arg(long)]
+    check: bool
   }
-  else {
-    run_prompt();
+}
+
+fn main() -> ExitCode {
+  let cli = match Cli::try_parse() {
+    Ok( cli ) => cli,
+    Err( e ) => {
+      let _ = e.print();
+      // --help and --version also arrive here, but aren't usage errors
+      return ExitCode::from( if e.use_stderr() { 64 } else { 0 } );
+    }
+  };
+  let status = with_stack( move || match cli.command {
+    Some( Command::Run { script, args, vm, max_depth, budget, max_memory, verbose, trace } ) => {
+      let backend = if vm { Backend::ByteCode } else { Backend::TreeWalk };
+      run_file( &script, &args, backend, Limits { max_depth, budget, max_memory }, verbose, trace )
+    },
+    Some( Command::Check { script } ) => check_file( &script ),
+    Some( Command::Ast { script, json } ) => print_ast( &script, json ),
+    Some( Command::Compile { script } ) => compile_file( &script ),
+    Some( Command::Disasm { script } ) => disasm_file( &script ),
+    Some( Command::Test { dir, vm } ) => {
+      let backend = if vm { Backend::ByteCode } else { Backend::TreeWalk };
+      test_dir( &dir, backend )
+    },
+    Some( Command::Fmt { scripts, check } ) => format_files( &scripts, check ),
+    Some( Command::Repl ) | None => run_prompt()
+  } );
+  ExitCode::from( status.exit_code() )
+}
+
+fn parse_max_depth( arg: &str ) -> Result<usize, String> {
+  let depth: usize = arg.parse().map_err( | e | format!( "{}", e ) )?;
+  if depth > MAX_DEPTH {
+    return Err( format!( "the interpreter's stack has room for at most {} calls", MAX_DEPTH ) );
   }
-}
\ No newline at end of file
+  Ok( depth )
+}
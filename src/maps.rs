@@ -0,0 +1,252 @@
+///////////////////////////////
+// private module rlox::maps //
+///////////////////////////////
+
+
+/////////
+// use //
+/////////
+
+use std::{cmp::Ordering, collections::BTreeMap, fmt::Display};
+
+use crate::{byte_code::{NativeFunction, Value, Vm}, error::Error, eval::Eval, interpreter::Interpreter,
+  native::{Arity, Native}, util::RcMut};
+
+
+//////////////////
+// declarations //
+//////////////////
+
+/// The hashable values that may key a Map, shared by both the backends.
+///
+/// Keys are kept in order (nil, then Bools, Numbers and Strings), so printing a
+/// Map or listing its keys is deterministic.
+#[derive(Clone, Debug)]
+pub enum MapKey {
+  Nil,
+  Bool( bool ),
+  Number( f64 ),
+  String( String )
+}
+
+
+/////////////////////
+// implementations //
+/////////////////////
+
+impl MapKey {
+  pub fn from_eval( eval: &Eval ) -> Result<MapKey, Error> {
+    match eval {
+      Eval::Nil => Ok( MapKey::Nil ),
+      Eval::Bool( b ) => Ok( MapKey::Bool( *b ) ),
+      Eval::Number( x ) => Ok( MapKey::number( *x ) ),
+      Eval::StringLiteral( s ) => Ok( MapKey::String( s.clone() ) ),
+      other => Err( key_error( other.get_type_name() ) )
+    }
+  }
+  pub fn to_eval( &self ) -> Eval {
+    match self {
+      MapKey::Nil => Eval::Nil,
+      MapKey::Bool( b ) => Eval::Bool( *b ),
+      MapKey::Number( x ) => Eval::Number( *x ),
+      MapKey::String( s ) => Eval::StringLiteral( s.clone() )
+    }
+  }
+  pub fn from_value( value: &Value ) -> Result<MapKey, Error> {
+    match value {
+      Value::Nil => Ok( MapKey::Nil ),
+      Value::Boolean( b ) => Ok( MapKey::Bool( *b ) ),
+      Value::Number( x ) => Ok( MapKey::number( *x ) ),
+      Value::String( s ) => Ok( MapKey::String( s.clone() ) ),
+      other => Err( key_error( other.get_type_name() ) )
+    }
+  }
+  pub fn to_value( &self ) -> Value {
+    match self {
+      MapKey::Nil => Value::Nil,
+      MapKey::Bool( b ) => Value::Boolean( *b ),
+      MapKey::Number( x ) => Value::Number( *x ),
+      MapKey::String( s ) => Value::String( s.clone() )
+    }
+  }
+  fn number( x: f64 ) -> MapKey {
+    // -0 == 0, so they must be the same key
+    MapKey::Number( if x == 0.0 { 0.0 } else { x } )
+  }
+  fn rank( &self ) -> u8 {
+    match self {
+      MapKey::Nil => 0,
+      MapKey::Bool( _ ) => 1,
+      MapKey::Number( _ ) => 2,
+      MapKey::String( _ ) => 3
+    }
+  }
+}
+
+impl Ord for MapKey {
+  fn cmp( &self, other: &MapKey ) -> Ordering {
+    match ( self, other ) {
+      ( MapKey::Bool( x ), MapKey::Bool( y ) ) => x.cmp( y ),
+      ( MapKey::Number( x ), MapKey::Number( y ) ) => x.total_cmp( y ),
+      ( MapKey::String( x ), MapKey::String( y ) ) => x.cmp( y ),
+      _ => self.rank().cmp( &other.rank() )
+    }
+  }
+}
+
+impl PartialOrd for MapKey {
+  fn partial_cmp( &self, other: &MapKey ) -> Option<Ordering> {
+    Some( self.cmp( other ) )
+  }
+}
+
+impl PartialEq for MapKey {
+  fn eq( &self, other: &MapKey ) -> bool {
+    self.cmp( other ) == Ordering::Equal
+  }
+}
+
+impl Eq for MapKey {}
+
+impl Display for MapKey {
+  fn fmt( &self, f: &mut std::fmt::Formatter<'_> ) -> std::fmt::Result {
+    write!( f, "{}", self.to_eval().repr() )
+  }
+}
+
+/// Looks up the method `name` on Maps.
+pub fn map_method( name: &str ) -> Option<Native> {
+  let method = match name {
+    "len" => Native::new( name, Arity::Fixed( 0 ), len ),
+    "keys" => Native::new( name, Arity::Fixed( 0 ), keys ),
+    "values" => Native::new( name, Arity::Fixed( 0 ), values ),
+    "contains" => Native::new( name, Arity::Fixed( 1 ), contains ),
+    "remove" => Native::new( name, Arity::Fixed( 1 ), remove ),
+    _ => return None
+  };
+  Some( method )
+}
+
+/// Evaluates `map[ key ]`.
+pub fn get_key( map: &RcMut<BTreeMap<MapKey, Eval>>, key: &Eval ) -> Result<Eval, Error> {
+  let key = MapKey::from_eval( key )?;
+  map.view().get( &key ).cloned().ok_or_else( || missing_error( &key ) )
+}
+
+/// Evaluates `map[ key ] = value`, adding `key` if it's new.
+pub fn set_key( map: &RcMut<BTreeMap<MapKey, Eval>>, key: &Eval, value: Eval ) -> Result<Eval, Error> {
+  let key = MapKey::from_eval( key )?;
+  map.clone().view_mut().insert( key, value.clone() );
+  Ok( value )
+}
+
+/// The byte code counterpart of `map_method`.
+pub fn vm_map_method( name: &str ) -> Option<NativeFunction> {
+  let method = match name {
+    "len" => NativeFunction::new( name, Arity::Fixed( 0 ), vm_len ),
+    "keys" => NativeFunction::new( name, Arity::Fixed( 0 ), vm_keys ),
+    "values" => NativeFunction::new( name, Arity::Fixed( 0 ), vm_values ),
+    "contains" => NativeFunction::new( name, Arity::Fixed( 1 ), vm_contains ),
+    "remove" => NativeFunction::new( name, Arity::Fixed( 1 ), vm_remove ),
+    _ => return None
+  };
+  Some( method )
+}
+
+/// The byte code counterpart of `get_key`.
+pub fn vm_get_key( map: &RcMut<BTreeMap<MapKey, Value>>, key: &Value ) -> Result<Value, Error> {
+  let key = MapKey::from_value( key )?;
+  map.view().get( &key ).cloned().ok_or_else( || missing_error( &key ) )
+}
+
+/// The byte code counterpart of `set_key`.
+pub fn vm_set_key( map: &RcMut<BTreeMap<MapKey, Value>>, key: &Value, value: Value ) -> Result<Value, Error> {
+  let key = MapKey::from_value( key )?;
+  map.clone().view_mut().insert( key, value.clone() );
+  Ok( value )
+}
+
+
+/////////////////////////////////
+// shared by both the backends //
+/////////////////////////////////
+
+fn key_error( found: String ) -> Error {
+  Error::from_msg( format!( "Map keys must be Numbers, Strings, Bools or nil, but found a {}.", found ) )
+}
+
+fn missing_error( key: &MapKey ) -> Error {
+  Error::from_msg( format!( "Key {} is not in the Map.", key ) )
+}
+
+
+///////////////////////////
+// tree-walk interpreter //
+///////////////////////////
+
+fn receiver( args: &[Eval] ) -> RcMut<BTreeMap<MapKey, Eval>> {
+  match &args[ 0 ] {
+    Eval::Map( map ) => map.clone(),
+    _ => unreachable!( "Internal error: Map methods are only bound to Maps." )
+  }
+}
+
+fn len( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  Ok( Eval::Number( receiver( args ).view().len() as f64 ) )
+}
+
+fn keys( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  let keys = receiver( args ).view().keys().map( | key | key.to_eval() ).collect();
+  Ok( Eval::List( RcMut::new( keys ) ) )
+}
+
+fn values( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  let values = receiver( args ).view().values().cloned().collect();
+  Ok( Eval::List( RcMut::new( values ) ) )
+}
+
+fn contains( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  let key = MapKey::from_eval( &args[ 1 ] )?;
+  Ok( Eval::Bool( receiver( args ).view().contains_key( &key ) ) )
+}
+
+fn remove( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  let key = MapKey::from_eval( &args[ 1 ] )?;
+  receiver( args ).view_mut().remove( &key ).ok_or_else( || missing_error( &key ) )
+}
+
+
+///////////////
+// byte code //
+///////////////
+
+fn vm_receiver( args: &[Value] ) -> RcMut<BTreeMap<MapKey, Value>> {
+  match &args[ 0 ] {
+    Value::Map( map ) => map.clone(),
+    _ => unreachable!( "Internal error: Map methods are only bound to Maps." )
+  }
+}
+
+fn vm_len( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  Ok( Value::Number( vm_receiver( args ).view().len() as f64 ) )
+}
+
+fn vm_keys( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  let keys = vm_receiver( args ).view().keys().map( | key | key.to_value() ).collect();
+  Ok( Value::List( RcMut::new( keys ) ) )
+}
+
+fn vm_values( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  let values = vm_receiver( args ).view().values().cloned().collect();
+  Ok( Value::List( RcMut::new( values ) ) )
+}
+
+fn vm_contains( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  let key = MapKey::from_value( &args[ 1 ] )?;
+  Ok( Value::Boolean( vm_receiver( args ).view().contains_key( &key ) ) )
+}
+
+fn vm_remove( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  let key = MapKey::from_value( &args[ 1 ] )?;
+  vm_receiver( args ).view_mut().remove( &key ).ok_or_else( || missing_error( &key ) )
+}
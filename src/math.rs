@@ -0,0 +1,315 @@
+///////////////////////////////
+// private module rlox::math //
+///////////////////////////////
+
+
+/////////
+// use //
+/////////
+
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{byte_code::{NativeFunction, Value, Vm, VmModule}, error::Error, eval::{Eval, Module},
+  interpreter::Interpreter, native::{Arity, Native, NativeFn}, prelude::type_error};
+
+
+/////////////////////
+// implementations //
+/////////////////////
+
+/// Builds the `math` module: numeric functions and constants over `Eval::Number`.
+pub fn math_module() -> Eval {
+  let natives = [
+    Native::new( "sqrt", Arity::Fixed( 1 ), sqrt ),
+    Native::new( "pow", Arity::Fixed( 2 ), pow ),
+    Native::new( "floor", Arity::Fixed( 1 ), floor ),
+    Native::new( "ceil", Arity::Fixed( 1 ), ceil ),
+    Native::new( "round", Arity::Fixed( 1 ), round ),
+    Native::new( "abs", Arity::Fixed( 1 ), abs ),
+    Native::new( "min", Arity::Variadic, min ),
+    Native::new( "max", Arity::Variadic, max ),
+    Native::new( "sin", Arity::Fixed( 1 ), sin ),
+    Native::new( "cos", Arity::Fixed( 1 ), cos ),
+    Native::new( "tan", Arity::Fixed( 1 ), tan ),
+    Native::new( "asin", Arity::Fixed( 1 ), asin ),
+    Native::new( "acos", Arity::Fixed( 1 ), acos ),
+    Native::new( "atan", Arity::Fixed( 1 ), atan ),
+    Native::new( "atan2", Arity::Fixed( 2 ), atan2 ),
+    Native::new( "exp", Arity::Fixed( 1 ), exp ),
+    Native::new( "log", Arity::Fixed( 1 ), log ),
+    Native::new( "log2", Arity::Fixed( 1 ), log2 ),
+    Native::new( "log10", Arity::Fixed( 1 ), log10 ),
+    Native::new( "is_nan", Arity::Fixed( 1 ), is_nan ),
+  ];
+  let mut members: HashMap<String, Eval> = HashMap::new();
+  for native in natives {
+    members.insert( native.name().into(), Eval::Native( Rc::new( native ) ) );
+  }
+  members.insert( "pi".into(), Eval::Number( std::f64::consts::PI ) );
+  members.insert( "e".into(), Eval::Number( std::f64::consts::E ) );
+  members.insert( "inf".into(), Eval::Number( f64::INFINITY ) );
+  members.insert( "nan".into(), Eval::Number( f64::NAN ) );
+  Eval::Module( Rc::new( Module { name: "math".into(), members } ) )
+}
+
+/// The byte code counterpart of `math_module`.
+pub fn vm_math_module() -> Value {
+  let natives = [
+    NativeFunction::new( "sqrt", Arity::Fixed( 1 ), vm_sqrt ),
+    NativeFunction::new( "pow", Arity::Fixed( 2 ), vm_pow ),
+    NativeFunction::new( "floor", Arity::Fixed( 1 ), vm_floor ),
+    NativeFunction::new( "ceil", Arity::Fixed( 1 ), vm_ceil ),
+    NativeFunction::new( "round", Arity::Fixed( 1 ), vm_round ),
+    NativeFunction::new( "abs", Arity::Fixed( 1 ), vm_abs ),
+    NativeFunction::new( "min", Arity::Variadic, vm_min ),
+    NativeFunction::new( "max", Arity::Variadic, vm_max ),
+    NativeFunction::new( "sin", Arity::Fixed( 1 ), vm_sin ),
+    NativeFunction::new( "cos", Arity::Fixed( 1 ), vm_cos ),
+    NativeFunction::new( "tan", Arity::Fixed( 1 ), vm_tan ),
+    NativeFunction::new( "asin", Arity::Fixed( 1 ), vm_asin ),
+    NativeFunction::new( "acos", Arity::Fixed( 1 ), vm_acos ),
+    NativeFunction::new( "atan", Arity::Fixed( 1 ), vm_atan ),
+    NativeFunction::new( "atan2", Arity::Fixed( 2 ), vm_atan2 ),
+    NativeFunction::new( "exp", Arity::Fixed( 1 ), vm_exp ),
+    NativeFunction::new( "log", Arity::Fixed( 1 ), vm_log ),
+    NativeFunction::new( "log2", Arity::Fixed( 1 ), vm_log2 ),
+    NativeFunction::new( "log10", Arity::Fixed( 1 ), vm_log10 ),
+    NativeFunction::new( "is_nan", Arity::Fixed( 1 ), vm_is_nan ),
+  ];
+  let mut members: HashMap<String, Value> = HashMap::new();
+  for native in natives {
+    members.insert( native.name().into(), Value::Native( Rc::new( native ) ) );
+  }
+  members.insert( "pi".into(), Value::Number( std::f64::consts::PI ) );
+  members.insert( "e".into(), Value::Number( std::f64::consts::E ) );
+  members.insert( "inf".into(), Value::Number( f64::INFINITY ) );
+  members.insert( "nan".into(), Value::Number( f64::NAN ) );
+  Value::Module( Rc::new( VmModule { name: "math".into(), members } ) )
+}
+
+
+/////////////
+// helpers //
+/////////////
+
+fn number( fun: &str, arg: &Eval ) -> Result<f64, Error> {
+  match arg {
+    Eval::Number( x ) => Ok( *x ),
+    other => Err( type_error( &format!( "math.{}", fun ), "Number", other.get_type_name() ) )
+  }
+}
+
+fn vm_number( fun: &str, arg: &Value ) -> Result<f64, Error> {
+  match arg {
+    Value::Number( x ) => Ok( *x ),
+    other => Err( type_error( &format!( "math.{}", fun ), "Number", other.get_type_name() ) )
+  }
+}
+
+fn unary( fun: &str, args: &[Eval], op: fn( f64 ) -> f64 ) -> Result<Eval, Error> {
+  Ok( Eval::Number( op( number( fun, &args[ 0 ] )? ) ) )
+}
+
+fn binary( fun: &str, args: &[Eval], op: fn( f64, f64 ) -> f64 ) -> Result<Eval, Error> {
+  Ok( Eval::Number( op( number( fun, &args[ 0 ] )?, number( fun, &args[ 1 ] )? ) ) )
+}
+
+fn fold( fun: &str, args: &[Eval], op: fn( f64, f64 ) -> f64 ) -> Result<Eval, Error> {
+  if args.is_empty() {
+    return Err( Error::from_msg( format!( "math.{}() expects at least one argument.", fun ) ) );
+  }
+  let mut result = number( fun, &args[ 0 ] )?;
+  for arg in &args[ 1.. ] {
+    result = op( result, number( fun, arg )? );
+  }
+  Ok( Eval::Number( result ) )
+}
+
+fn vm_unary( fun: &str, args: &[Value], op: fn( f64 ) -> f64 ) -> Result<Value, Error> {
+  Ok( Value::Number( op( vm_number( fun, &args[ 0 ] )? ) ) )
+}
+
+fn vm_binary( fun: &str, args: &[Value], op: fn( f64, f64 ) -> f64 ) -> Result<Value, Error> {
+  Ok( Value::Number( op( vm_number( fun, &args[ 0 ] )?, vm_number( fun, &args[ 1 ] )? ) ) )
+}
+
+fn vm_fold( fun: &str, args: &[Value], op: fn( f64, f64 ) -> f64 ) -> Result<Value, Error> {
+  if args.is_empty() {
+    return Err( Error::from_msg( format!( "math.{}() expects at least one argument.", fun ) ) );
+  }
+  let mut result = vm_number( fun, &args[ 0 ] )?;
+  for arg in &args[ 1.. ] {
+    result = op( result, vm_number( fun, arg )? );
+  }
+  Ok( Value::Number( result ) )
+}
+
+
+///////////////
+// functions //
+///////////////
+
+fn sqrt( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  unary( "sqrt", args, f64::sqrt )
+}
+
+fn pow( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  binary( "pow", args, f64::powf )
+}
+
+fn floor( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  unary( "floor", args, f64::floor )
+}
+
+fn ceil( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  unary( "ceil", args, f64::ceil )
+}
+
+fn round( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  unary( "round", args, f64::round )
+}
+
+fn abs( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  unary( "abs", args, f64::abs )
+}
+
+fn min( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  fold( "min", args, f64::min )
+}
+
+fn max( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  fold( "max", args, f64::max )
+}
+
+fn sin( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  unary( "sin", args, f64::sin )
+}
+
+fn cos( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  unary( "cos", args, f64::cos )
+}
+
+fn tan( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  unary( "tan", args, f64::tan )
+}
+
+fn asin( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  unary( "asin", args, f64::asin )
+}
+
+fn acos( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  unary( "acos", args, f64::acos )
+}
+
+fn atan( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  unary( "atan", args, f64::atan )
+}
+
+fn atan2( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  binary( "atan2", args, f64::atan2 )
+}
+
+fn exp( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  unary( "exp", args, f64::exp )
+}
+
+fn log( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  unary( "log", args, f64::ln )
+}
+
+fn log2( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  unary( "log2", args, f64::log2 )
+}
+
+fn log10( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  unary( "log10", args, f64::log10 )
+}
+
+fn is_nan( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  Ok( Eval::Bool( number( "is_nan", &args[ 0 ] )?.is_nan() ) )
+}
+
+
+/////////////////////////
+// functions on the VM //
+/////////////////////////
+
+fn vm_sqrt( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_unary( "sqrt", args, f64::sqrt )
+}
+
+fn vm_pow( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_binary( "pow", args, f64::powf )
+}
+
+fn vm_floor( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_unary( "floor", args, f64::floor )
+}
+
+fn vm_ceil( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_unary( "ceil", args, f64::ceil )
+}
+
+fn vm_round( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_unary( "round", args, f64::round )
+}
+
+fn vm_abs( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_unary( "abs", args, f64::abs )
+}
+
+fn vm_min( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_fold( "min", args, f64::min )
+}
+
+fn vm_max( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_fold( "max", args, f64::max )
+}
+
+fn vm_sin( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_unary( "sin", args, f64::sin )
+}
+
+fn vm_cos( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_unary( "cos", args, f64::cos )
+}
+
+fn vm_tan( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_unary( "tan", args, f64::tan )
+}
+
+fn vm_asin( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_unary( "asin", args, f64::asin )
+}
+
+fn vm_acos( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_unary( "acos", args, f64::acos )
+}
+
+fn vm_atan( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_unary( "atan", args, f64::atan )
+}
+
+fn vm_atan2( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_binary( "atan2", args, f64::atan2 )
+}
+
+fn vm_exp( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_unary( "exp", args, f64::exp )
+}
+
+fn vm_log( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_unary( "log", args, f64::ln )
+}
+
+fn vm_log2( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_unary( "log2", args, f64::log2 )
+}
+
+fn vm_log10( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  vm_unary( "log10", args, f64::log10 )
+}
+
+fn vm_is_nan( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  Ok( Value::Boolean( vm_number( "is_nan", &args[ 0 ] )?.is_nan() ) )
+}
@@ -0,0 +1,84 @@
+////////////////////////////////
+// public module rlox::native //
+////////////////////////////////
+
+
+/////////
+// use //
+/////////
+
+use std::fmt::Display;
+
+use crate::{error::Error, eval::Eval, interpreter::Interpreter};
+
+
+//////////////////
+// declarations //
+//////////////////
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Arity {
+  Fixed( usize ),
+  Variadic
+}
+
+/// A Rust function that scripts can call like any other Lox function.
+///
+/// Errors returned without a line number are reported at the call site.
+pub trait NativeFn {
+  fn name( &self ) -> &str;
+  fn arity( &self ) -> Arity;
+  fn call( &self, interpreter: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error>;
+}
+
+/// The common case of a `NativeFn`: a plain function with a name and an arity.
+pub struct Native {
+  name: String,
+  arity: Arity,
+  fun: fn( &mut Interpreter, &[Eval] ) -> Result<Eval, Error>
+}
+
+
+/////////////////////
+// implementations //
+/////////////////////
+
+impl Arity {
+  pub fn accepts( &self, count: usize ) -> bool {
+    match self {
+      Arity::Fixed( n ) => *n == count,
+      Arity::Variadic => true
+    }
+  }
+}
+
+impl Display for Arity {
+  fn fmt( &self, f: &mut std::fmt::Formatter<'_> ) -> std::fmt::Result {
+    match self {
+      Arity::Fixed( n ) => write!( f, "{}", n ),
+      Arity::Variadic => write!( f, "..." )
+    }
+  }
+}
+
+impl Native {
+  pub fn new( name: &str, arity: Arity, fun: fn( &mut Interpreter, &[Eval] ) -> Result<Eval, Error> ) -> Native {
+    Native {
+      name: name.into(),
+      arity,
+      fun
+    }
+  }
+}
+
+impl NativeFn for Native {
+  fn name( &self ) -> &str {
+    &self.name
+  }
+  fn arity( &self ) -> Arity {
+    self.arity
+  }
+  fn call( &self, interpreter: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+    ( self.fun )( interpreter, args )
+  }
+}
@@ -11,6 +11,7 @@ use crate::token::{Token, TokenType};
 use crate::expr::*;
 use crate::stmt::*;
 use crate::error::Error;
+use crate::cst::{Event, NodeKind};
 
 
 //////////////////
@@ -21,6 +22,10 @@ pub struct Parser {
   tokens: Vec<Token>,
   stmts: Vec<Stmt>,
   current: usize,
+
+  // the shape of the parse, for the concrete syntax tree
+  events: Vec<Event>,
+  errors: Vec<Error>,
   had_error: bool
 }
 
@@ -37,6 +42,8 @@ impl Parser {
       tokens: Vec::new(),
       stmts: Vec::new(),
       current: 0,
+      events: Vec::new(),
+      errors: Vec::new(),
       had_error: false
     }  
   }
@@ -61,12 +68,36 @@ impl Parser {
     self.stmts.clear();
     ( stmts, self.had_error )
   }
+  pub fn parse_expression( &mut self, tokens: Vec<Token> ) -> ( Option<Expr>, bool ) {
+    self.restart( tokens );
+    let result = self.parse_expr().and_then( | expr | {
+      self.peek_assert( TokenType::Eof, " after the expression." )?;
+      Ok( expr )
+    } );
+    match result {
+      Ok( expr ) => ( Some( expr ), self.had_error ),
+      Err( error ) => {
+        self.emit_error( &error );
+        ( None, self.had_error )
+      }
+    }
+  }
   fn restart( &mut self, tokens: Vec<Token> ) {
     self.tokens = tokens;
     self.stmts.clear();
     self.current = 0;
+    self.events.clear();
+    self.errors.clear();
     self.had_error = false;
   }
+  pub fn take_errors( &mut self ) -> Vec<Error> {
+    std::mem::take( &mut self.errors )
+  }
+
+  /// How the last parse went about it, to build a `cst::SyntaxNode` from.
+  pub fn take_events( &mut self ) -> Vec<Event> {
+    std::mem::take( &mut self.events )
+  }
 
   // decl => fun_decl | var_decl | stmt
   fn parse_decl( &mut self ) -> ParseStmtResult {
@@ -80,17 +111,27 @@ impl Parser {
     }
   }
 
-  // fun_decl => "fun" id "(" parameters? ")" block_stmt
-  // parameters => id ( "," id )*
+  // fun_decl => "fun" id function
   fn parse_fun_decl( &mut self ) -> ParseStmtResult {
 
     // "fun"
+    self.start( NodeKind::FunDecl );
     self.pop();
 
     // id
     let name = self.parse_id()?;
 
+    let function = self.parse_function( name )?;
+    self.finish();
+    Ok( Stmt::Function( function ) )
+  }
+
+  // function => "(" parameters? ")" block_stmt
+  // parameters => id ( "," id )*
+  fn parse_function( &mut self, name: Token ) -> Result<Function, Error> {
+
     // "("
+    self.start( NodeKind::Params );
     self.pop_assert( TokenType::LeftParen, " to open function parameter list." )?;
 
     let mut params: Vec<Token> = Vec::new();
@@ -106,14 +147,15 @@ impl Parser {
 
       // ")"
       self.pop_assert( TokenType::RightParen, " to close function parameter list." )?;
-    } 
+    }
+    self.finish();
 
     // block_stmt
     self.peek_assert( TokenType::LeftBrace, " to begin function body." )?;
     if let Stmt::Block( block ) = self.parse_block_stmt()? {
 
       // success
-      return Ok( Stmt::Function( Function{ name, params, body: block.statements } ) )
+      return Ok( Function{ name, params, body: block.statements, end: block.end } )
     }
     unreachable!()
   }
@@ -122,6 +164,7 @@ impl Parser {
   fn parse_var_decl( &mut self ) -> ParseStmtResult {
 
     //  "var"
+    self.start( NodeKind::VarDecl );
     self.pop();
 
     // id
@@ -139,7 +182,8 @@ impl Parser {
 
     // ";"
     self.pop_assert( TokenType::Semicolon, " to complete the variable declaration." )?;
-    
+    self.finish();
+
     return Ok( Stmt::Var( Var{ name, init } ) );
   }
   fn parse_id( &mut self ) -> Result<Token, Error> {
@@ -156,6 +200,8 @@ impl Parser {
   ///       | while_stmt
   ///       | for_stmt
   ///       | return_stmt
+  ///       | break_stmt
+  ///       | continue_stmt
   fn parse_stmt( &mut self ) -> ParseStmtResult {
     match self.peek_type() {
       TokenType::Print => self.parse_print_stmt(),
@@ -164,6 +210,10 @@ impl Parser {
       TokenType::While => self.parse_while_stmt(),
       TokenType::For => self.parse_for_stmt(),
       TokenType::Return => self.parse_return_stmt(),
+      TokenType::Break => self.parse_break_stmt(),
+      TokenType::Continue => self.parse_continue_stmt(),
+      TokenType::Throw => self.parse_throw_stmt(),
+      TokenType::Try => self.parse_try_stmt(),
       _ => Ok( self.parse_expr_stmt()? )
     }
   }
@@ -172,6 +222,7 @@ impl Parser {
   fn parse_print_stmt( &mut self ) -> ParseStmtResult {
     
     // "print"
+    self.start( NodeKind::PrintStmt );
     self.pop();
 
     // expr
@@ -179,6 +230,7 @@ impl Parser {
     
     // ";"
     self.pop_assert( TokenType::Semicolon, " to complete the print statement." )?;
+    self.finish();
 
     // success
     Ok( Stmt::Print( Print{ expression } ) )
@@ -188,6 +240,7 @@ impl Parser {
   fn parse_block_stmt( &mut self ) -> ParseStmtResult {
     
     // "{"
+    self.start( NodeKind::Block );
     let line = self.pop().line;
 
     // decl*
@@ -197,16 +250,19 @@ impl Parser {
     }
 
     // "}"
+    let end = self.peek().line;
     self.pop_assert( TokenType::RightBrace, " to complete the block statement." )?;
+    self.finish();
 
     // success
-    Ok( Stmt::Block( Block{ statements: stmts, line } ) )
+    Ok( Stmt::Block( Block{ statements: stmts, line, end } ) )
   }
 
   // if_stmt = "if" "(" expr ")" stmt ( "else" stmt )?
   fn parse_if_stmt( &mut self ) -> ParseStmtResult {
 
     // "if"
+    self.start( NodeKind::IfStmt );
     self.pop();
 
     // "("
@@ -228,6 +284,7 @@ impl Parser {
     } else {
       None
     };
+    self.finish();
 
     // success
     Ok( Stmt::If( If{ condition, then_branch, else_branch } ) )
@@ -238,7 +295,8 @@ impl Parser {
   fn parse_while_stmt( &mut self ) -> ParseStmtResult {
 
     // "while"
-    self.pop();
+    self.start( NodeKind::WhileStmt );
+    let keyword = self.pop();
 
     // "("
     self.pop_assert( TokenType::LeftParen, " to open the while-statement condition-clause." )?;
@@ -251,20 +309,30 @@ impl Parser {
 
     // stmt
     let body = Box::new( self.parse_stmt()? );
+    self.finish();
 
     // success
-    Ok( Stmt::While( While{ condition, body } ) )
+    Ok( Stmt::While( While{ keyword, condition, body, increment: None } ) )
 
   }
 
   // for_stmt => "for" "(" ( var_decl | expr_stmt | ";" ) ( expr )? ";" ( expr )? ")" stmt
+  //           | for_in_stmt
   fn parse_for_stmt( &mut self ) -> ParseStmtResult {
 
     // "for"
-    let line = self.pop().line;
+    let mark = self.mark();
+    let keyword = self.pop();
+    let line = keyword.line;
 
     // "("
     self.pop_assert( TokenType::LeftParen, " to open the for-statement control-clause." )?;
+
+    if self.is_for_in() {
+      self.precede( mark, NodeKind::ForInStmt );
+      return self.parse_for_in_stmt( keyword );
+    }
+    self.precede( mark, NodeKind::ForStmt );
     
     // ( var_decl | expr_stmt | ";" )
     let init: Option<Stmt> = 
@@ -306,25 +374,14 @@ impl Parser {
 
     // stmt
     let mut body = self.parse_stmt()?;
-    
-    // desugar incr
-    if incr.is_some() {
-      body = Stmt::Block( Block{
-        statements: [
-          body,
-          Stmt::Expression( Expression{
-            expression: incr.unwrap()
-          } )
-        ].to_vec(), line
-      } );
-    }
+    self.finish();
 
-    // desugar condition
-    body = Stmt::While( While { condition, body: Box::new( body ) } );
+    // desugar condition and incr
+    body = Stmt::While( While { keyword, condition, body: Box::new( body ), increment: incr } );
 
     // desugar init
     if init.is_some() {
-      body = Stmt::Block( Block { statements: [ init.unwrap(), body ].to_vec(), line } );
+      body = Stmt::Block( Block { statements: [ init.unwrap(), body ].to_vec(), line, end: line } );
     }
 
     // success
@@ -332,14 +389,37 @@ impl Parser {
 
   }
 
+  // for_in_stmt => "for" "(" id "in" expr ")" stmt
+  fn parse_for_in_stmt( &mut self, keyword: Token ) -> ParseStmtResult {
+
+    // id "in"
+    let name = self.pop();
+    self.pop();
+
+    // expr
+    let iterable = self.parse_expr()?;
+
+    // ")"
+    self.pop_assert( TokenType::RightParen, " to close the for-in control-clause." )?;
+
+    // stmt
+    let body = Box::new( self.parse_stmt()? );
+    self.finish();
+
+    // success
+    Ok( Stmt::ForIn( ForIn { name, keyword, iterable, body } ) )
+  }
+
   // expr_stmt => expr ";"
   fn parse_expr_stmt( &mut self ) -> ParseStmtResult {
     
     // expr
+    self.start( NodeKind::ExprStmt );
     let expression = self.parse_expr()?;
             
     // ";"
     self.pop_assert( TokenType::Semicolon, " to complete the expression statement." )?;
+    self.finish();
     
     // success
     Ok( Stmt::Expression( Expression{ expression } ) )
@@ -350,6 +430,7 @@ impl Parser {
   fn parse_return_stmt( &mut self ) -> ParseStmtResult {
 
     // "return"
+    self.start( NodeKind::ReturnStmt );
     let keyword = self.pop();
 
     // expr? ";"
@@ -360,30 +441,117 @@ impl Parser {
       self.pop_assert( TokenType::Semicolon, " to complete the return statment." )?;
       Some( expr )
     };
+    self.finish();
 
     Ok( Stmt::Return( Return{ keyword, value } ) )
   }
 
+  // break_stmt => "break" ";"
+  fn parse_break_stmt( &mut self ) -> ParseStmtResult {
+    self.start( NodeKind::BreakStmt );
+    let keyword = self.pop();
+    self.pop_assert( TokenType::Semicolon, " to complete the break statement." )?;
+    self.finish();
+    Ok( Stmt::Break( Break{ keyword } ) )
+  }
+
+  // continue_stmt => "continue" ";"
+  fn parse_continue_stmt( &mut self ) -> ParseStmtResult {
+    self.start( NodeKind::ContinueStmt );
+    let keyword = self.pop();
+    self.pop_assert( TokenType::Semicolon, " to complete the continue statement." )?;
+    self.finish();
+    Ok( Stmt::Continue( Continue{ keyword } ) )
+  }
+
+  // throw_stmt => "throw" expr ";"
+  fn parse_throw_stmt( &mut self ) -> ParseStmtResult {
+    self.start( NodeKind::ThrowStmt );
+    let keyword = self.pop();
+    let value = self.parse_expr()?;
+    self.pop_assert( TokenType::Semicolon, " to complete the throw statement." )?;
+    self.finish();
+    Ok( Stmt::Throw( Throw{ keyword, value } ) )
+  }
+
+  // try_stmt => "try" block_stmt ( "catch" "(" id ")" block_stmt )? ( "finally" block_stmt )?
+  fn parse_try_stmt( &mut self ) -> ParseStmtResult {
+
+    // "try" block_stmt
+    self.start( NodeKind::TryStmt );
+    let keyword = self.pop();
+    let body = self.parse_block()?;
+
+    // ( "catch" "(" id ")" block_stmt )?
+    let catch = if self.peek_type() == TokenType::Catch {
+      self.start( NodeKind::CatchClause );
+      self.pop();
+      self.pop_assert( TokenType::LeftParen, " after 'catch'." )?;
+      let name = self.parse_id()?;
+      self.pop_assert( TokenType::RightParen, " after the caught name." )?;
+      let body = self.parse_block()?;
+      self.finish();
+      Some( Catch{ name, body } )
+    } else {
+      None
+    };
+
+    // ( "finally" block_stmt )?
+    let finally = if self.peek_type() == TokenType::Finally {
+      self.start( NodeKind::FinallyClause );
+      self.pop();
+      let body = self.parse_block()?;
+      self.finish();
+      Some( body )
+    } else {
+      None
+    };
+
+    if catch.is_none() && finally.is_none() {
+      return Err( self.make_error( "Expected 'catch' or 'finally' after the try block.".into() ) );
+    }
+    self.finish();
+    Ok( Stmt::Try( Try{ keyword, body, catch, finally } ) )
+  }
+  fn parse_block( &mut self ) -> Result<Block, Error> {
+    self.peek_assert( TokenType::LeftBrace, " to begin the block." )?;
+    match self.parse_block_stmt()? {
+      Stmt::Block( block ) => Ok( block ),
+      _ => unreachable!()
+    }
+  }
+
   // expr => assign
   fn parse_expr( &mut self ) -> ParseExprResult {
     self.parse_assign()
   }
 
-  // assign  => ( id "=" assign ) | logical_or
+  // assign  => ( ( id | call "[" expr "]" ) "=" assign ) | logical_or
   fn parse_assign( &mut self ) -> ParseExprResult {
 
+    let mark = self.mark();
     let expr = self.parse_or()?;
 
     if self.peek_type() == TokenType::Equal {
+      self.precede( mark, NodeKind::Assign );
       let equal = self.pop();
       let rhs = self.parse_assign()?;
+      self.finish();
       match expr {
         Expr::Variable( lhs ) => {
           Ok( Expr::Assign( Assign {
             lhs,
             rhs: Box::new( rhs )
           } ) )
-        }
+        },
+        Expr::Index( index ) => {
+          Ok( Expr::SetIndex( SetIndex {
+            object: index.object,
+            bracket: index.bracket,
+            index: index.index,
+            value: Box::new( rhs )
+          } ) )
+        },
         _ => Err( Error::from_token( &equal,
           "Cannot assign to the expression on the left hand side.".to_string() ) )
       }
@@ -395,11 +563,14 @@ impl Parser {
 
   // or  => and ( "or" and )*
   fn parse_or( &mut self ) -> ParseExprResult {
+    let mark = self.mark();
     let mut expr = self.parse_and()?;
     loop {
        if self.is_or() {
+        self.precede( mark, NodeKind::Binary );
         let operator = self.pop();
         let right = self.parse_and()?;
+        self.finish();
         expr = Expr::Binary( Binary {
           left: Box::new( expr ),
           operator,
@@ -414,11 +585,14 @@ impl Parser {
 
   // and => eq ( "and" eq )*
   fn parse_and( &mut self ) -> ParseExprResult {
+    let mark = self.mark();
     let mut expr = self.parse_eq()?;
     loop {
        if self.is_and() {
+        self.precede( mark, NodeKind::Binary );
         let operator = self.pop();
         let right = self.parse_eq()?;
+        self.finish();
         expr = Expr::Binary( Binary {
           left: Box::new( expr ),
           operator,
@@ -433,11 +607,14 @@ impl Parser {
 
   // eq => cmp ( ( "==" | "!=" ) cmp )*
   fn parse_eq( &mut self ) -> ParseExprResult {
+    let mark = self.mark();
     let mut expr = self.parse_cmp()?;
     loop {
        if self.is_eq() {
+        self.precede( mark, NodeKind::Binary );
         let operator = self.pop();
         let right = self.parse_cmp()?;
+        self.finish();
         expr = Expr::Binary( Binary {
           
           left: Box::new( expr ),
@@ -453,11 +630,14 @@ impl Parser {
 
   // cmp => term ( ( "<" | "<=" | ">" | ">=" ) term )*
   fn parse_cmp( &mut self ) -> ParseExprResult {
+    let mark = self.mark();
     let mut expr = self.parse_term()?;
     loop {
       if self.is_cmp() {
+        self.precede( mark, NodeKind::Binary );
         let operator = self.pop();
         let right = self.parse_term()?;
+        self.finish();
         expr = Expr::Binary( Binary {
           left: Box::new( expr ),
           operator,
@@ -472,11 +652,14 @@ impl Parser {
   
   // term => factor ( ( "+" | "-" ) factor )*
   fn parse_term( &mut self ) -> ParseExprResult {
+    let mark = self.mark();
     let mut expr = self.parse_factor()?;
     loop {
       if self.is_term() {
+        self.precede( mark, NodeKind::Binary );
         let operator = self.pop();
         let right = self.parse_factor()?;
+        self.finish();
         expr = Expr::Binary( Binary {
           left: Box::new( expr ),
           operator,
@@ -491,11 +674,14 @@ impl Parser {
 
   // factor => unary ( ( "*" | "/" ) unary )*
   fn parse_factor( &mut self ) -> ParseExprResult {
+    let mark = self.mark();
     let mut expr = self.parse_unary()?;
     loop {
       if self.is_factor()  {
+        self.precede( mark, NodeKind::Binary );
         let operator = self.pop();
         let right = self.parse_unary()?;
+        self.finish();
         expr = Expr::Binary( Binary {
           
           left: Box::new( expr ),
@@ -512,28 +698,55 @@ impl Parser {
   // unary => ( ( "!" | "-" ) unary ) | call
   fn parse_unary( &mut self ) -> ParseExprResult {
     if self.is_unary() {
-        Ok( Expr::Unary( Unary {
-          operator: self.pop().clone(),
-          right: Box::new( self.parse_unary()? )
-        } ) )
+        self.start( NodeKind::Unary );
+        let operator = self.pop();
+        let right = Box::new( self.parse_unary()? );
+        self.finish();
+        Ok( Expr::Unary( Unary { operator, right } ) )
     } else {
       self.parse_call()
     }
   }
 
-  // call => grouping ( "(" arguments? ")" )* | grouping
+  // call => grouping ( "(" arguments? ")" | "." id | "[" expr "]" )* | grouping
   fn parse_call( &mut self ) -> ParseExprResult {
 
+    let mark = self.mark();
     let mut expr = self.parse_grouping()?;
 
     // println!( "parse_call() before loop: expr = {}", expr.to_string( self.sm ) );
 
     loop {
-      if self.pop_if( TokenType::LeftParen ) {
+      if self.peek_type() == TokenType::LeftParen {
         // println!( "parse_call() found '('" );
+        self.precede( mark, NodeKind::Call );
+        self.pop();
         expr = self.parse_arguments( expr )?;
+        self.finish();
         // println!( "parse_call() args = {}", expr.to_string( self.sm ) );
       }
+      else if self.peek_type() == TokenType::Dot {
+        self.precede( mark, NodeKind::Get );
+        self.pop();
+        let name = self.parse_id()?;
+        self.finish();
+        expr = Expr::Get( Get {
+          object: Box::new( expr ),
+          name
+        } );
+      }
+      else if self.peek_type() == TokenType::LeftBracket {
+        self.precede( mark, NodeKind::Index );
+        self.pop();
+        let index = self.parse_expr()?;
+        let bracket = self.pop_assert( TokenType::RightBracket, " to close the index." )?;
+        self.finish();
+        expr = Expr::Index( Index {
+          object: Box::new( expr ),
+          bracket,
+          index: Box::new( index )
+        } );
+      }
       else {
         break;
       }
@@ -564,31 +777,147 @@ impl Parser {
     } ) )
   }
   
-  // grouping => ( "(" expr ")" ) | primary
+  // grouping => lambda | ( "(" expr ")" ) | list | map | primary
   fn parse_grouping( &mut self ) -> ParseExprResult {
-    if self.is_grouping() {
+    if self.is_lambda() {
+      self.parse_lambda()
+    } else if self.is_arrow_lambda() {
+      self.parse_arrow_lambda()
+    } else if self.is_grouping() {
+      self.start( NodeKind::Grouping );
       self.pop();
       let expr = Expr::Grouping( Grouping {
         expression: Box::new( self.parse_expr()? )
       } );
       self.pop_assert( TokenType::RightParen, " to close the grouping." )?;
+      self.finish();
       Ok( expr )
+    } else if self.is_list() {
+      self.parse_list()
+    } else if self.is_map() {
+      self.parse_map()
     } else {
       self.parse_primary()
     }
   }
 
+  // lambda => "fun" function
+  fn parse_lambda( &mut self ) -> ParseExprResult {
+
+    // "fun"
+    self.start( NodeKind::Lambda );
+    let keyword = self.pop();
+
+    // function
+    let function = self.parse_function( Parser::lambda_name( &keyword ) )?;
+    self.finish();
+
+    Ok( Expr::Lambda( Lambda { keyword, function } ) )
+  }
+
+  // arrow_lambda => "(" parameters? ")" "=>" expr
+  fn parse_arrow_lambda( &mut self ) -> ParseExprResult {
+
+    // "(" parameters? ")"
+    self.start( NodeKind::ArrowLambda );
+    self.start( NodeKind::Params );
+    self.pop();
+    let mut params: Vec<Token> = Vec::new();
+    while !self.pop_if( TokenType::RightParen ) {
+      params.push( self.parse_id()? );
+      self.pop_if( TokenType::Comma );
+    }
+    self.finish();
+
+    // "=>"
+    let keyword = self.pop();
+
+    // expr [ which the body returns ]
+    let value = self.parse_expr()?;
+    self.finish();
+    let body = vec![ Stmt::Return( Return { keyword: keyword.clone(), value: Some( value ) } ) ];
+
+    let name = Parser::lambda_name( &keyword );
+    let end = keyword.line;
+    Ok( Expr::Lambda( Lambda { keyword, function: Function { name, params, body, end } } ) )
+  }
+  fn lambda_name( keyword: &Token ) -> Token {
+    Token { token_type: TokenType::Identifier, lexeme: "lambda".into(), line: keyword.line }
+  }
+
+  // list => "[" ( expr ( "," expr )* )? "]"
+  fn parse_list( &mut self ) -> ParseExprResult {
+
+    // "["
+    self.start( NodeKind::List );
+    let bracket = self.pop();
+
+    // ( expr ( "," expr )* )?
+    let mut elements: Vec<Box<Expr>> = Vec::new();
+    if self.peek_type() != TokenType::RightBracket {
+      loop {
+        elements.push( Box::new( self.parse_expr()? ) );
+        if !self.pop_if( TokenType::Comma ) {
+          break;
+        }
+      }
+    }
+
+    // "]"
+    self.pop_assert( TokenType::RightBracket, " to close the list." )?;
+    self.finish();
+
+    Ok( Expr::List( List { bracket, elements } ) )
+  }
+
+  // map => "{" ( entry ( "," entry )* )? "}"
+  // entry => expr ":" expr
+  fn parse_map( &mut self ) -> ParseExprResult {
+
+    // "{"
+    self.start( NodeKind::Map );
+    let brace = self.pop();
+
+    // ( entry ( "," entry )* )?
+    let mut entries: Vec<( Box<Expr>, Box<Expr> )> = Vec::new();
+    if self.peek_type() != TokenType::RightBrace {
+      loop {
+        self.start( NodeKind::MapEntry );
+        let key = self.parse_expr()?;
+        self.pop_assert( TokenType::Colon, " between a map key and its value." )?;
+        let value = self.parse_expr()?;
+        self.finish();
+        entries.push( ( Box::new( key ), Box::new( value ) ) );
+        if !self.pop_if( TokenType::Comma ) {
+          break;
+        }
+      }
+    }
+
+    // "}"
+    self.pop_assert( TokenType::RightBrace, " to close the map." )?;
+    self.finish();
+
+    Ok( Expr::Map( Map { brace, entries } ) )
+  }
+
   // primary => "true" | "false" | "nil" | IDENTIFIER | NUMBER | STRING
   fn parse_primary( &mut self ) -> ParseExprResult {
     if self.is_primary() {
       if self.is_id() {
+        self.start( NodeKind::Variable );
+        let name = self.pop();
+        self.finish();
         Ok( Expr::Variable( Variable {
-          name: self.pop(),
+          name,
           jump: -1
         } ) )
       } else {
+        self.start( NodeKind::Literal );
+        let value = self.pop();
+        self.finish();
         Ok( Expr::Literal( Literal {
-          value: self.pop()
+          value
         } ) )
       }
     } else {
@@ -596,11 +925,14 @@ impl Parser {
     }
   }
   fn is_fun_decl( &self ) -> bool {
-    self.peek_type() == TokenType::Fun
+    self.peek_type() == TokenType::Fun && self.peek_type_at( 1 ) == TokenType::Identifier
   }
   fn is_var_decl( &self ) -> bool {
     self.peek_type() == TokenType::Var
   }
+  fn is_for_in( &self ) -> bool {
+    self.peek_type() == TokenType::Identifier && self.peek_type_at( 1 ) == TokenType::In
+  }
   fn is_or( &self ) -> bool {
     self.peek_type() == TokenType::Or
   }
@@ -652,9 +984,34 @@ impl Parser {
       _ => false
     }
   }
+  fn is_lambda( &self ) -> bool {
+    self.peek_type() == TokenType::Fun
+  }
+
+  // "(" ( id ( "," id )* )? ")" "=>"    [ decided before consuming anything ]
+  fn is_arrow_lambda( &self ) -> bool {
+    if self.peek_type() != TokenType::LeftParen {
+      return false;
+    }
+    let mut ahead = 1;
+    if self.peek_type_at( ahead ) == TokenType::Identifier {
+      ahead += 1;
+      while self.peek_type_at( ahead ) == TokenType::Comma
+        && self.peek_type_at( ahead + 1 ) == TokenType::Identifier {
+        ahead += 2;
+      }
+    }
+    self.peek_type_at( ahead ) == TokenType::RightParen && self.peek_type_at( ahead + 1 ) == TokenType::Arrow
+  }
   fn is_grouping( &self ) -> bool {
     self.peek_type() == TokenType::LeftParen
   }
+  fn is_list( &self ) -> bool {
+    self.peek_type() == TokenType::LeftBracket
+  }
+  fn is_map( &self ) -> bool {
+    self.peek_type() == TokenType::LeftBrace
+  }
   fn is_primary( &self ) -> bool {
     match self.peek_type() {
       TokenType::False
@@ -670,6 +1027,7 @@ impl Parser {
   fn pop( &mut self ) -> Token {
     if !self.is_at_end() {
       self.current += 1;
+      self.events.push( Event::Token );
     }
     self.previous().clone()
   }
@@ -700,6 +1058,12 @@ impl Parser {
   fn peek_type( &self ) -> TokenType {
     self.peek().token_type
   }
+  fn peek_type_at( &self, ahead: usize ) -> TokenType {
+    match self.tokens.get( self.current + ahead ) {
+      Some( token ) => token.token_type,
+      None => TokenType::Eof
+    }
+  }
   fn peek_assert( &mut self, tt: TokenType, loc: &str ) -> Result<(), Error> {
     if self.peek_type() != tt {
       Err( self.make_error( format!( "Expected '{}'{}", tt.get_lexeme(), loc ) ) )
@@ -718,8 +1082,22 @@ impl Parser {
   fn make_error( &self, msg: String ) -> Error {
     Error::from_token( self.peek(), msg )
   }
+  fn start( &mut self, kind: NodeKind ) {
+    self.events.push( Event::Start( kind ) );
+  }
+  fn finish( &mut self ) {
+    self.events.push( Event::Finish );
+  }
+
+  // where the next node would start, should it turn out to enclose what follows
+  fn mark( &self ) -> usize {
+    self.events.len()
+  }
+  fn precede( &mut self, mark: usize, kind: NodeKind ) {
+    self.events.insert( mark, Event::Start( kind ) );
+  }
   fn emit_error( &mut self, error: &Error ) {
-    eprintln!( "[line {}] Error{}: {}", error.line, error.loc, error.msg );
+    self.errors.push( error.clone() );
     self.had_error = true;
   }
 }
\ No newline at end of file
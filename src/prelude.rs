@@ -0,0 +1,342 @@
+//////////////////////////////////
+// private module rlox::prelude //
+//////////////////////////////////
+
+
+/////////
+// use //
+/////////
+
+use std::{io::{self, BufRead, Write}, mem, rc::Rc, time::{SystemTime, UNIX_EPOCH}};
+
+use crate::{byte_code::{Value, Vm}, env::Env, error::Error, eval::{Cursor, Eval}, interpreter::Interpreter,
+  math::{math_module, vm_math_module}, native::{Arity, Native, NativeFn}, token::{Token, TokenType}, util::RcMut};
+
+
+/////////////////////
+// implementations //
+/////////////////////
+
+/// Binds the built-in functions every script starts with into a global `env`.
+pub fn define_prelude( env: &mut Env ) {
+  let natives = [
+    Native::new( "clock", Arity::Fixed( 0 ), clock ),
+    Native::new( "str", Arity::Fixed( 1 ), str ),
+    Native::new( "num", Arity::Fixed( 1 ), num ),
+    Native::new( "type", Arity::Fixed( 1 ), type_ ),
+    Native::new( "len", Arity::Fixed( 1 ), len ),
+    Native::new( "range", Arity::Variadic, range ),
+    Native::new( "iterator", Arity::Fixed( 2 ), iterator ),
+    Native::new( "input", Arity::Fixed( 0 ), input ),
+    Native::new( "exit", Arity::Fixed( 1 ), exit ),
+    Native::new( "assert", Arity::Fixed( 2 ), assert ),
+    Native::new( "assert_eq", Arity::Fixed( 2 ), assert_eq ),
+  ];
+  for native in natives {
+    let name = Token { token_type: TokenType::Identifier, lexeme: native.name().into(), line: 0 };
+    env.define_symbol( &name, &Eval::Native( Rc::new( native ) ) );
+  }
+  let math = Token { token_type: TokenType::Identifier, lexeme: "math".into(), line: 0 };
+  env.define_symbol( &math, &math_module() );
+}
+
+/// The byte code counterpart of `define_prelude`.
+pub fn define_vm_prelude( vm: &mut Vm ) {
+  vm.define_native( "clock", Arity::Fixed( 0 ), vm_clock );
+  vm.define_native( "str", Arity::Fixed( 1 ), vm_str );
+  vm.define_native( "num", Arity::Fixed( 1 ), vm_num );
+  vm.define_native( "type", Arity::Fixed( 1 ), vm_type );
+  vm.define_native( "len", Arity::Fixed( 1 ), vm_len );
+  vm.define_native( "range", Arity::Variadic, vm_range );
+  vm.define_native( "input", Arity::Fixed( 0 ), vm_input );
+  vm.define_native( "exit", Arity::Fixed( 1 ), vm_exit );
+  vm.define_native( "assert", Arity::Fixed( 2 ), vm_assert );
+  vm.define_native( "assert_eq", Arity::Fixed( 2 ), vm_assert_eq );
+  vm.define_global( "math", vm_math_module() );
+}
+
+
+/////////////////////////////////
+// shared by both the backends //
+/////////////////////////////////
+
+fn seconds_since_epoch() -> f64 {
+  SystemTime::now().duration_since( UNIX_EPOCH ).map_or( 0.0, | d | d.as_secs_f64() )
+}
+
+pub fn parse_number( fun: &str, s: &str ) -> Result<f64, Error> {
+  s.trim().parse::<f64>().map_err( | _ | Error::from_msg( format!( "{}() cannot convert \"{}\" to a Number.", fun, s ) ) )
+}
+
+fn read_line() -> Option<String> {
+  let _ = io::stdout().flush();
+  let mut line = String::new();
+  match io::stdin().lock().read_line( &mut line ) {
+    Ok( 0 ) | Err( _ ) => None,
+    Ok( _ ) => Some( line.trim_end_matches( [ '\n', '\r' ] ).to_string() )
+  }
+}
+
+// a process exit status is a byte, so anything outside 0..=255 would be cut down to one [ 256 to success ]
+fn exit_code( code: f64 ) -> Result<i32, Error> {
+  if code.fract() != 0.0 || !( 0.0..=255.0 ).contains( &code ) {
+    return Err( Error::from_msg( format!( "exit() expects a whole number from 0 to 255, but found {}.", code ) ) );
+  }
+  let _ = io::stdout().flush();
+  Ok( code as i32 )
+}
+
+/// The `( start, end, step )` of `range( [ start, ] end [, step ] )`.
+fn range_bounds( args: &[Option<f64>] ) -> Result<( f64, f64, f64 ), Error> {
+  let args: Vec<f64> = match args.iter().copied().collect::<Option<Vec<f64>>>() {
+    Some( args ) => args,
+    None => return Err( Error::from_msg( "range() expects Number arguments.".into() ) )
+  };
+  let ( start, end, step ) = match args[ .. ] {
+    [ end ] => ( 0.0, end, 1.0 ),
+    [ start, end ] => ( start, end, 1.0 ),
+    [ start, end, step ] => ( start, end, step ),
+    _ => return Err( Error::from_msg( format!( "range() expects 1 to 3 arguments, but found {}.", args.len() ) ) )
+  };
+  if step == 0.0 {
+    return Err( Error::from_msg( "range() step cannot be zero.".into() ) );
+  }
+  Ok( ( start, end, step ) )
+}
+
+/// How many numbers a range counts through, for charging the work up front.
+fn range_len( ( start, end, step ): ( f64, f64, f64 ) ) -> u64 {
+  let len = ( ( end - start ) / step ).ceil();
+
+  // [ NaN bounds count through nothing, as the loop below does ]
+  if len > 0.0 {
+    len as u64
+  } else {
+    0
+  }
+}
+
+/// The numbers a range counts through, `end` excluded.
+///
+/// Counting stops after `range_len` of them and a spare, for a step too small to
+/// move a number as large as `start` would otherwise count forever.
+fn range_numbers( bounds: ( f64, f64, f64 ) ) -> Result<Vec<f64>, Error> {
+  let ( start, end, step ) = bounds;
+  let cap = range_len( bounds ).saturating_add( 1 );
+  let mut result = Vec::new();
+  let mut x = start;
+  while ( step > 0.0 && x < end ) || ( step < 0.0 && x > end ) {
+    if result.len() as u64 >= cap {
+      return Err( Error::from_msg( format!( "range() step {} is too small to count from {} to {}.", step, start, end ) ) );
+    }
+    result.push( x );
+    x += step;
+  }
+  Ok( result )
+}
+
+fn assertion_error( msg: String ) -> Error {
+  Error::from_msg( format!( "Assertion failed: {}", msg ) )
+}
+
+pub fn type_error( fun: &str, expected: &str, found: String ) -> Error {
+  Error::from_msg( format!( "{}() expects a {}, but found a {}.", fun, expected, found ) )
+}
+
+
+///////////////////////////
+// tree-walk interpreter //
+///////////////////////////
+
+fn clock( _: &mut Interpreter, _: &[Eval] ) -> Result<Eval, Error> {
+  Ok( Eval::Number( seconds_since_epoch() ) )
+}
+
+fn str( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  Ok( Eval::StringLiteral( args[ 0 ].to_string() ) )
+}
+
+fn num( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  match &args[ 0 ] {
+    Eval::Number( x ) => Ok( Eval::Number( *x ) ),
+    Eval::StringLiteral( s ) => Ok( Eval::Number( parse_number( "num", s )? ) ),
+    other => Err( type_error( "num", "String", other.get_type_name() ) )
+  }
+}
+
+fn type_( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  Ok( Eval::StringLiteral( args[ 0 ].get_type_name() ) )
+}
+
+fn len( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  match &args[ 0 ] {
+    Eval::StringLiteral( s ) => Ok( Eval::Number( s.chars().count() as f64 ) ),
+    Eval::List( list ) => Ok( Eval::Number( list.view().len() as f64 ) ),
+    Eval::Map( map ) => Ok( Eval::Number( map.view().len() as f64 ) ),
+    other => Err( type_error( "len", "String, List or Map", other.get_type_name() ) )
+  }
+}
+
+fn range( interpreter: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  let args: Vec<Option<f64>> = args.iter().map( | arg | match arg {
+    Eval::Number( x ) => Some( *x ),
+    _ => None
+  } ).collect();
+  let bounds = range_bounds( &args )?;
+  interpreter.spend( range_len( bounds ) )?;
+  interpreter.make_room( ( range_len( bounds ) as usize ).saturating_mul( mem::size_of::<Eval>() ) )?;
+  let numbers = range_numbers( bounds )?.into_iter().map( Eval::Number ).collect();
+  Ok( Eval::List( RcMut::new( numbers ) ) )
+}
+
+fn iterator( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  for arg in args {
+    if !matches!( arg, Eval::Fun( _, _ ) | Eval::Native( _ ) | Eval::Method( _ ) ) {
+      return Err( type_error( "iterator", "function", arg.get_type_name() ) );
+    }
+  }
+  Ok( Eval::Iterator( Rc::new( Cursor { has_next: args[ 0 ].clone(), next: args[ 1 ].clone() } ) ) )
+}
+
+fn input( _: &mut Interpreter, _: &[Eval] ) -> Result<Eval, Error> {
+  Ok( read_line().map_or( Eval::Nil, Eval::StringLiteral ) )
+}
+
+fn exit( interpreter: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  match &args[ 0 ] {
+    Eval::Number( x ) => {
+      interpreter.exit( exit_code( *x )? );
+      Ok( Eval::Nil )
+    },
+    other => Err( type_error( "exit", "Number", other.get_type_name() ) )
+  }
+}
+
+fn assert( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  if args[ 0 ].is_truthy() {
+    Ok( Eval::Nil )
+  } else {
+    Err( assertion_error( args[ 1 ].to_string() ) )
+  }
+}
+
+fn assert_eq( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  if equal( &args[ 0 ], &args[ 1 ] ) {
+    Ok( Eval::Nil )
+  } else {
+    Err( assertion_error( format!( "{} != {}", args[ 0 ].repr(), args[ 1 ].repr() ) ) )
+  }
+}
+
+// structural, so lists and maps compare by contents
+fn equal( a: &Eval, b: &Eval ) -> bool {
+  match ( a, b ) {
+    ( Eval::Number( x ), Eval::Number( y ) ) => x == y,
+    ( Eval::StringLiteral( x ), Eval::StringLiteral( y ) ) => x == y,
+    ( Eval::Bool( x ), Eval::Bool( y ) ) => x == y,
+    ( Eval::Nil, Eval::Nil ) => true,
+    ( Eval::List( x ), Eval::List( y ) ) => {
+      let ( x, y ) = ( x.view(), y.view() );
+      x.len() == y.len() && x.iter().zip( y.iter() ).all( | ( a, b ) | equal( a, b ) )
+    },
+    ( Eval::Map( x ), Eval::Map( y ) ) => {
+      let ( x, y ) = ( x.view(), y.view() );
+      x.len() == y.len() && x.iter().zip( y.iter() ).all( | ( ( k, a ), ( l, b ) ) | k == l && equal( a, b ) )
+    },
+    _ => false
+  }
+}
+
+
+///////////////
+// byte code //
+///////////////
+
+fn vm_clock( _: &mut Vm, _: &[Value] ) -> Result<Value, Error> {
+  Ok( Value::Number( seconds_since_epoch() ) )
+}
+
+fn vm_str( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  Ok( Value::String( args[ 0 ].to_string() ) )
+}
+
+fn vm_num( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  match &args[ 0 ] {
+    Value::Number( x ) => Ok( Value::Number( *x ) ),
+    Value::String( s ) => Ok( Value::Number( parse_number( "num", s )? ) ),
+    other => Err( type_error( "num", "String", other.get_type_name() ) )
+  }
+}
+
+fn vm_type( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  Ok( Value::String( args[ 0 ].get_type_name() ) )
+}
+
+fn vm_len( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  match &args[ 0 ] {
+    Value::String( s ) => Ok( Value::Number( s.chars().count() as f64 ) ),
+    Value::List( list ) => Ok( Value::Number( list.view().len() as f64 ) ),
+    Value::Map( map ) => Ok( Value::Number( map.view().len() as f64 ) ),
+    other => Err( type_error( "len", "String, List or Map", other.get_type_name() ) )
+  }
+}
+
+fn vm_range( vm: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  let args: Vec<Option<f64>> = args.iter().map( | arg | match arg {
+    Value::Number( x ) => Some( *x ),
+    _ => None
+  } ).collect();
+  let bounds = range_bounds( &args )?;
+  vm.spend( range_len( bounds ) )?;
+  vm.make_room( ( range_len( bounds ) as usize ).saturating_mul( mem::size_of::<Value>() ) )?;
+  let numbers = range_numbers( bounds )?.into_iter().map( Value::Number ).collect();
+  Ok( Value::List( RcMut::new( numbers ) ) )
+}
+
+fn vm_input( _: &mut Vm, _: &[Value] ) -> Result<Value, Error> {
+  Ok( read_line().map_or( Value::Nil, Value::String ) )
+}
+
+fn vm_exit( vm: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  match &args[ 0 ] {
+    Value::Number( x ) => {
+      vm.exit( exit_code( *x )? );
+      Ok( Value::Nil )
+    },
+    other => Err( type_error( "exit", "Number", other.get_type_name() ) )
+  }
+}
+
+fn vm_assert( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  if args[ 0 ].is_truthy() {
+    Ok( Value::Nil )
+  } else {
+    Err( assertion_error( args[ 1 ].to_string() ) )
+  }
+}
+
+fn vm_assert_eq( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  if vm_equal( &args[ 0 ], &args[ 1 ] ) {
+    Ok( Value::Nil )
+  } else {
+    Err( assertion_error( format!( "{} != {}", args[ 0 ].repr(), args[ 1 ].repr() ) ) )
+  }
+}
+
+fn vm_equal( a: &Value, b: &Value ) -> bool {
+  match ( a, b ) {
+    ( Value::Number( x ), Value::Number( y ) ) => x == y,
+    ( Value::String( x ), Value::String( y ) ) => x == y,
+    ( Value::Boolean( x ), Value::Boolean( y ) ) => x == y,
+    ( Value::Nil, Value::Nil ) => true,
+    ( Value::List( x ), Value::List( y ) ) => {
+      let ( x, y ) = ( x.view(), y.view() );
+      x.len() == y.len() && x.iter().zip( y.iter() ).all( | ( a, b ) | vm_equal( a, b ) )
+    },
+    ( Value::Map( x ), Value::Map( y ) ) => {
+      let ( x, y ) = ( x.view(), y.view() );
+      x.len() == y.len() && x.iter().zip( y.iter() ).all( | ( ( k, a ), ( l, b ) ) | k == l && vm_equal( a, b ) )
+    },
+    _ => false
+  }
+}
@@ -0,0 +1,282 @@
+///////////////////////////////
+// private module rlox::repl //
+///////////////////////////////
+
+
+/////////
+// use //
+/////////
+
+use std::{env, fs, path::PathBuf, time::Instant};
+
+use rustyline::{completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+  history::DefaultHistory, validate::Validator, Context, Editor, Helper};
+
+use crate::{ast_printer::AstPrinter, byte_code::Compiler, error::{report_warnings, Diagnostics},
+  expr::Expr, interpreter::Interpreter, parser::Parser, resolver::Resolver, scanner::Scanner,
+  stmt::Stmt, token::{Token, TokenType}, util::is_alphanumeric};
+
+
+//////////////////
+// declarations //
+//////////////////
+
+pub struct Repl {
+  interpreter: Interpreter,
+  resolver: Resolver,
+  timing: bool,
+  quit: bool,
+
+  // what the session called `exit( code )` with, if it did
+  exit_code: Option<i32>
+}
+
+struct ReplHelper {
+  globals: Vec<String>
+}
+
+const HELP: &str = "\
+:load <file>  execute a file into the session
+:env          list the bound globals and their types
+:ast <expr>   print the parsed tree of an expression
+:bc <expr>    print the compiled byte code of an expression
+:reset        discard all globals and start over
+:time         toggle execution timing
+:quit         leave the prompt
+:help         show this message";
+
+
+/////////////////////
+// implementations //
+/////////////////////
+
+impl Repl {
+  pub fn new() -> Repl {
+    Repl {
+      interpreter: Interpreter::new(),
+      resolver: Resolver::new(),
+      timing: false,
+      quit: false,
+      exit_code: None
+    }
+  }
+  /// Reads and runs lines until `:quit`, the end of input or `exit()`, returning the code passed to the latter.
+  pub fn run( &mut self ) -> Option<i32> {
+    let mut editor: Editor<ReplHelper, DefaultHistory> = match Editor::new() {
+      Ok( editor ) => editor,
+      Err( e ) => {
+        eprintln!( "Error initialising the prompt: {}", e );
+        return None;
+      }
+    };
+    editor.set_helper( Some( ReplHelper { globals: Vec::new() } ) );
+    let history = Repl::history_path();
+    if let Some( path ) = &history {
+      // a missing history file just means this is the first session
+      let _ = editor.load_history( path );
+    }
+    while !self.quit {
+      println!();
+      match editor.readline( "> " ) {
+        Ok( input ) => {
+          let _ = editor.add_history_entry( input.as_str() );
+          self.handle_line( input );
+          if let Some( helper ) = editor.helper_mut() {
+            helper.globals = self.global_names();
+          }
+        },
+        Err( ReadlineError::Interrupted ) => continue,
+        Err( ReadlineError::Eof ) => break,
+        Err( e ) => {
+          eprintln!( "Error reading stdin: {}", e );
+          break;
+        }
+      }
+    }
+    if let Some( path ) = &history {
+      if let Err( e ) = editor.save_history( path ) {
+        eprintln!( "Error saving history: {}", e );
+      }
+    }
+    self.exit_code
+  }
+  fn history_path() -> Option<PathBuf> {
+    env::var_os( "HOME" )
+      .or_else( || env::var_os( "USERPROFILE" ) )
+      .map( | home | PathBuf::from( home ).join( ".rlox_history" ) )
+  }
+  fn global_names( &self ) -> Vec<String> {
+    self.interpreter.globals().into_iter().map( | ( name, _ ) | name ).collect()
+  }
+  fn handle_line( &mut self, input: String ) {
+    match input.trim().strip_prefix( ':' ) {
+      Some( command ) => {
+        let ( name, arg ) = command.split_once( char::is_whitespace ).unwrap_or( ( command, "" ) );
+        self.exec_command( name, arg.trim() );
+      },
+      None => {
+        self.exec_source( input, true );
+      }
+    }
+  }
+  fn exec_command( &mut self, name: &str, arg: &str ) {
+    match name {
+      "load" => self.load( arg ),
+      "env" => self.print_env(),
+      "ast" => self.print_ast( arg ),
+      "bc" => self.print_byte_code( arg ),
+      "reset" => {
+        self.interpreter.restart();
+        println!( "Session reset." );
+      },
+      "time" => {
+        self.timing = !self.timing;
+        println!( "Timing is {}.", if self.timing { "on" } else { "off" } );
+      },
+      "quit" => self.quit = true,
+      "help" => println!( "{}", HELP ),
+      _ => eprintln!( "Unknown command ':{}'. Type :help for a list of commands.", name )
+    }
+  }
+  fn exec_source( &mut self, src: String, is_prompt: bool ) -> bool {
+
+    // scanner / lexer
+    let mut scanner = Scanner::new();
+    let ( mut tokens, had_scan_error ) = scanner.scan( src );
+    if had_scan_error {
+      Diagnostics::new( scanner.take_errors() ).report();
+      return true;
+    }
+
+    // at the prompt, a trailing expression may omit its ";" and gets echoed
+    let echo = is_prompt && Repl::complete_bare_expression( &mut tokens );
+
+    // parser
+    let mut parser = Parser::new();
+    let ( mut stmts, had_parse_error ) = parser.parse( tokens );
+    if had_parse_error {
+      Diagnostics::new( parser.take_errors() ).report();
+      return true;
+    }
+    let echo = echo && matches!( stmts.last(), Some( Stmt::Expression( _ ) ) );
+
+    // resolver [ against the globals bound so far in this session ]
+    let globals = self.global_names();
+    let had_resolve_error = self.resolver.resolve_with_globals( &mut stmts, &globals );
+    report_warnings( &self.resolver.take_warnings() );
+    if had_resolve_error {
+      Diagnostics::new( self.resolver.take_errors() ).report();
+      return true;
+    }
+
+    // interpreter
+    let start = Instant::now();
+    let ( eval, had_error ) = self.interpreter.interpret( &stmts );
+    if let Some( code ) = self.interpreter.exit_code() {
+      self.exit_code = Some( code );
+      self.quit = true;
+    }
+    if had_error {
+      Diagnostics::new( self.interpreter.take_errors() ).report();
+    }
+    else if echo {
+      println!( "{}", eval.repr() );
+    }
+    if self.timing {
+      println!( "[Executed in {:?}]", start.elapsed() );
+    }
+    had_error
+  }
+  fn complete_bare_expression( tokens: &mut Vec<Token> ) -> bool {
+    let eof = tokens.len() - 1;
+    let needs_semicolon = match tokens.get( eof.wrapping_sub( 1 ) ) {
+      Some( last ) => !matches!( last.token_type, TokenType::Semicolon | TokenType::RightBrace ),
+      None => false
+    };
+    if needs_semicolon {
+      let line = tokens[ eof ].line;
+      tokens.insert( eof, Token { token_type: TokenType::Semicolon, lexeme: ";".into(), line } );
+    }
+    needs_semicolon
+  }
+  fn load( &mut self, path: &str ) {
+    match fs::read_to_string( path ) {
+      Ok( src ) => {
+        let had_error = self.exec_source( src, false );
+        if !had_error {
+          println!( "Loaded '{}'.", path );
+        }
+      },
+      Err( e ) => eprintln!( "Error reading file: {}", e )
+    }
+  }
+  fn print_env( &self ) {
+    for ( name, value ) in self.interpreter.globals() {
+      println!( "{}: {}", name, value.get_type_name() );
+    }
+  }
+  fn print_ast( &self, src: &str ) {
+    if let Some( expr ) = Repl::parse_expression( src ) {
+      println!( "{}", AstPrinter::new().print_expr( &expr ) );
+    }
+  }
+  fn print_byte_code( &self, src: &str ) {
+    if let Some( expr ) = Repl::parse_expression( src ) {
+      let mut compiler = Compiler::new();
+      let ( byte_code, had_codegen_error ) = compiler.compile_expression( &expr );
+      if had_codegen_error {
+        Diagnostics::new( compiler.take_errors() ).report();
+      }
+      else {
+        print!( "{}", byte_code.disassemble() );
+      }
+    }
+  }
+  fn parse_expression( src: &str ) -> Option<Expr> {
+    let mut scanner = Scanner::new();
+    let ( tokens, had_scan_error ) = scanner.scan( src.into() );
+    if had_scan_error {
+      Diagnostics::new( scanner.take_errors() ).report();
+      return None;
+    }
+    let mut parser = Parser::new();
+    let ( expr, had_parse_error ) = parser.parse_expression( tokens );
+    if had_parse_error {
+      Diagnostics::new( parser.take_errors() ).report();
+    }
+    expr
+  }
+}
+
+impl Completer for ReplHelper {
+  type Candidate = String;
+  fn complete( &self, line: &str, pos: usize, _ctx: &Context<'_> ) -> rustyline::Result<( usize, Vec<String> )> {
+
+    // complete the identifier-like word ending at the cursor
+    let start = line[ ..pos ].char_indices().rev()
+      .find( | ( _, c ) | !is_alphanumeric( *c ) )
+      .map_or( 0, | ( idx, c ) | idx + c.len_utf8() );
+    let prefix = &line[ start..pos ];
+    if prefix.is_empty() {
+      return Ok( ( start, Vec::new() ) );
+    }
+    let mut candidates: Vec<String> = Scanner::keywords()
+      .map( | keyword | keyword.to_string() )
+      .chain( self.globals.iter().cloned() )
+      .filter( | candidate | candidate.starts_with( prefix ) )
+      .collect();
+    candidates.sort();
+    candidates.dedup();
+    Ok( ( start, candidates ) )
+  }
+}
+
+impl Hinter for ReplHelper {
+  type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
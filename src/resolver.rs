@@ -16,6 +16,13 @@ use crate::error::Error;
 pub struct Resolver {
   scopes: Stack<HashMap<String, ResolveStatus>>,
   global_init_order: Vec<String>,
+  predefined: Vec<String>,
+  loop_depth: usize,
+
+  // whether globals may be declared again, as in a session
+  redeclare_globals: bool,
+  errors: Vec<Error>,
+  warnings: Vec<Error>,
   had_error: bool
 }
 
@@ -63,14 +70,36 @@ impl Resolver {
     Resolver {
       scopes: Stack::new(),
       global_init_order: Vec::new(),
+      predefined: Vec::new(),
+      loop_depth: 0,
+      redeclare_globals: false,
+      errors: Vec::new(),
+      warnings: Vec::new(),
       had_error: false
     }
   }
+  pub fn with_globals( globals: Vec<String> ) -> Resolver {
+    let mut resolver = Resolver::new();
+    resolver.predefined = globals;
+    resolver
+  }
   fn restart( &mut self ) {
     self.scopes.clear();
     self.global_init_order.clear();
+    self.loop_depth = 0;
+    self.errors.clear();
+    self.warnings.clear();
     self.had_error = false;
   }
+  pub fn take_errors( &mut self ) -> Vec<Error> {
+    std::mem::take( &mut self.errors )
+  }
+  /// The warnings by line, then name [ a scope's symbols come out of a HashMap in no set order ].
+  pub fn take_warnings( &mut self ) -> Vec<Error> {
+    let mut warnings = std::mem::take( &mut self.warnings );
+    warnings.sort_by( | a, b | ( a.line, &a.loc ).cmp( &( b.line, &b.loc ) ) );
+    warnings
+  }
   fn begin_scope( &mut self ) {
     self.scopes.push( HashMap::new() );
   }
@@ -79,8 +108,9 @@ impl Resolver {
     self.scopes.pop();
   }
   fn declare_name( &mut self, name: &Token ) -> Result<(), Error> {
+    let at_top_level = self.scopes.depth() == 1;
     let scope = self.scopes.peek_mut( 0 );
-    if scope.contains_key( &name.lexeme ) {
+    if scope.contains_key( &name.lexeme ) && !( self.redeclare_globals && at_top_level ) {
       return Err( Error::from_token( &name, "Name already in use.".into() ) );
     }
     scope.insert( name.lexeme.clone(), ResolveStatus::declare( name.line ) );
@@ -89,6 +119,14 @@ impl Resolver {
   fn define_name( &mut self, name: &Token ) {
     self.scopes.peek_mut( 0 ).get_mut( &name.lexeme ).unwrap().define();
   }
+  fn declare_globals( &mut self, globals: &Vec<String> ) {
+    for name in globals {
+      let mut status = ResolveStatus::declare( 0 );
+      status.define();
+      status.mark_as_read();
+      self.scopes.peek_mut( 0 ).insert( name.clone(), status );
+    }
+  }
   fn declare_define( &mut self, name: &Token ) -> Result<(), Error> {
     self.declare_name( name )?;
     self.define_name( name );
@@ -118,16 +156,37 @@ impl Resolver {
     }
     Ok( () )
   }
-  fn warn_unused( &self ) {
+  fn resolve_function( &mut self, function: &mut stmt::Function ) -> Result<(), Error> {
+    self.begin_scope();
+    for param in &function.params {
+      self.declare_define( param )?;
+    }
+
+    // a loop around the declaration doesn't make 'break' valid in the body
+    let loop_depth = std::mem::replace( &mut self.loop_depth, 0 );
+    self.resolve_stmts( &mut function.body )?;
+    self.loop_depth = loop_depth;
+    self.end_scope();
+    Ok( () )
+  }
+  fn warn_unused( &mut self ) {
     for ( name, status ) in self.scopes.peek( 0 ) {
       if status.is_unread() {
-        eprintln!( "[line {}] Warning at '{}': Symbol is defined but never used.", status.get_line(), name );
+        self.warnings.push( Error {
+          line: status.get_line(),
+          loc: format!( " at '{}'", name ),
+          msg: "Symbol is defined but never used.".into(),
+          trace: Vec::new()
+        } );
       }
     }
   }
   pub fn resolve( &mut self, stmts: &mut Vec<Stmt> ) -> bool {
     self.restart();
+    self.redeclare_globals = false;
     self.begin_scope();
+    let predefined = self.predefined.clone();
+    self.declare_globals( &predefined );
     match self.resolve_stmts( stmts ) {
       Ok( _ ) => {},
       Err( e ) => self.emit_error( &e ),
@@ -135,8 +194,22 @@ impl Resolver {
     self.end_scope();
     self.had_error
   }
+  /// Resolves one evaluation of a session, where `globals` are bound already and may be redeclared.
+  pub fn resolve_with_globals( &mut self, stmts: &mut Vec<Stmt>, globals: &Vec<String> ) -> bool {
+    self.restart();
+    self.redeclare_globals = true;
+    self.begin_scope();
+    self.declare_globals( globals );
+    match self.resolve_stmts( stmts ) {
+      Ok( _ ) => {},
+      Err( e ) => self.emit_error( &e ),
+    }
+    // globals outlive this call, so don't warn about the ones not read (yet)
+    self.scopes.clear();
+    self.had_error
+  }
   fn emit_error( &mut self, error: &Error ) {
-    eprintln!( "[line {}] Error{}: {}", error.line, error.loc, error.msg );
+    self.errors.push( error.clone() );
     self.had_error = true;
   }
 }
@@ -159,16 +232,44 @@ impl expr::MutVisitor<Result<(), Error>> for Resolver {
     }
     Ok( () )
   }
+  fn visit_get_expr_mut( &mut self, get: &mut expr::Get ) -> Result<(), Error> {
+    self.resolve_expr( &mut get.object )
+  }
   fn visit_grouping_expr_mut( &mut self, grouping: &mut expr::Grouping ) -> Result<(), Error> {
     self.resolve_expr( &mut grouping.expression )
   }
+  fn visit_index_expr_mut( &mut self, index: &mut expr::Index ) -> Result<(), Error> {
+    self.resolve_expr( &mut index.object )?;
+    self.resolve_expr( &mut index.index )
+  }
+  fn visit_lambda_expr_mut( &mut self, lambda: &mut expr::Lambda ) -> Result<(), Error> {
+    self.resolve_function( &mut lambda.function )
+  }
+  fn visit_list_expr_mut( &mut self, list: &mut expr::List ) -> Result<(), Error> {
+    for element in &mut list.elements {
+      self.resolve_expr( element.as_mut() )?;
+    }
+    Ok( () )
+  }
   fn visit_literal_expr_mut( &mut self, _literal: &mut expr::Literal ) -> Result<(), Error> {
     Ok( () )
   }
+  fn visit_map_expr_mut( &mut self, map: &mut expr::Map ) -> Result<(), Error> {
+    for ( key, value ) in &mut map.entries {
+      self.resolve_expr( key.as_mut() )?;
+      self.resolve_expr( value.as_mut() )?;
+    }
+    Ok( () )
+  }
   // fn visit_logical_expr_mut( &mut self, logical: &mut expr::Logical ) -> Result<(), Error> {
   //   self.resolve_expr( &mut logical.left )?;
   //   self.resolve_expr( &mut logical.right )
   // }
+  fn visit_set_index_expr_mut( &mut self, set_index: &mut expr::SetIndex ) -> Result<(), Error> {
+    self.resolve_expr( &mut set_index.object )?;
+    self.resolve_expr( &mut set_index.index )?;
+    self.resolve_expr( &mut set_index.value )
+  }
   fn visit_unary_expr_mut( &mut self, unary: &mut expr::Unary ) -> Result<(), Error> {
     self.resolve_expr( &mut unary.right )
   }
@@ -190,19 +291,35 @@ impl stmt::MutVisitor<Result<(), Error>> for Resolver {
     self.end_scope();
     Ok( () )
   }
+  fn visit_break_stmt_mut( &mut self, break_: &mut stmt::Break ) -> Result<(), Error> {
+    if self.loop_depth == 0 {
+      return Err( Error::from_token( &break_.keyword, "Cannot use 'break' outside of a loop.".into() ) );
+    }
+    Ok( () )
+  }
+  fn visit_continue_stmt_mut( &mut self, continue_: &mut stmt::Continue ) -> Result<(), Error> {
+    if self.loop_depth == 0 {
+      return Err( Error::from_token( &continue_.keyword, "Cannot use 'continue' outside of a loop.".into() ) );
+    }
+    Ok( () )
+  }
   fn visit_expression_stmt_mut( &mut self, expression: &mut stmt::Expression ) -> Result<(), Error> {
     self.resolve_expr( &mut expression.expression )
   }
-  fn visit_function_stmt_mut( &mut self, function: &mut stmt::Function ) -> Result<(), Error> {
-    self.declare_define( &function.name )?;
+  fn visit_for_in_stmt_mut( &mut self, for_in: &mut stmt::ForIn ) -> Result<(), Error> {
+    self.resolve_expr( &mut for_in.iterable )?;
     self.begin_scope();
-    for param in &function.params {
-      self.declare_define( param )?;
-    }
-    self.resolve_stmts( &mut function.body )?;
+    self.declare_define( &for_in.name )?;
+    self.loop_depth += 1;
+    self.resolve_stmt( &mut for_in.body )?;
+    self.loop_depth -= 1;
     self.end_scope();
     Ok( () )
   }
+  fn visit_function_stmt_mut( &mut self, function: &mut stmt::Function ) -> Result<(), Error> {
+    self.declare_define( &function.name )?;
+    self.resolve_function( function )
+  }
   fn visit_if_stmt_mut( &mut self, if_: &mut stmt::If ) -> Result<(), Error> {
     self.resolve_expr( &mut if_.condition )?;
     self.resolve_stmt( &mut if_.then_branch )?;
@@ -220,6 +337,22 @@ impl stmt::MutVisitor<Result<(), Error>> for Resolver {
     }
     Ok( () )
   }
+  fn visit_throw_stmt_mut( &mut self, throw: &mut stmt::Throw ) -> Result<(), Error> {
+    self.resolve_expr( &mut throw.value )
+  }
+  fn visit_try_stmt_mut( &mut self, try_: &mut stmt::Try ) -> Result<(), Error> {
+    self.visit_block_stmt_mut( &mut try_.body )?;
+    if let Some( catch ) = &mut try_.catch {
+      self.begin_scope();
+      self.declare_define( &catch.name )?;
+      self.visit_block_stmt_mut( &mut catch.body )?;
+      self.end_scope();
+    }
+    if let Some( finally ) = &mut try_.finally {
+      self.visit_block_stmt_mut( finally )?;
+    }
+    Ok( () )
+  }
   fn visit_var_stmt_mut( &mut self, var: &mut stmt::Var ) -> Result<(), Error> {
     self.declare_name( &var.name )?;
     if let Some( expr ) = &mut var.init {
@@ -230,6 +363,12 @@ impl stmt::MutVisitor<Result<(), Error>> for Resolver {
   }
   fn visit_while_stmt_mut( &mut self, while_: &mut stmt::While ) -> Result<(), Error> {
     self.resolve_expr( &mut while_.condition )?;
-    self.resolve_stmt( &mut while_.body )
+    self.loop_depth += 1;
+    self.resolve_stmt( &mut while_.body )?;
+    self.loop_depth -= 1;
+    if let Some( increment ) = &mut while_.increment {
+      self.resolve_expr( increment )?;
+    }
+    Ok( () )
   }
 }
\ No newline at end of file
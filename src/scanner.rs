@@ -9,6 +9,8 @@
 
 use crate::util::{is_alpha, is_alphanumeric, is_digit, ifte, char_at, substring};
 use crate::token::{TokenType, Token};
+use crate::cst::{split_trivia, SyntaxToken};
+use crate::error::Error;
 
 
 //////////////////
@@ -21,9 +23,28 @@ pub struct Scanner {
   start: usize,
   current: usize,
   line: u32,
+
+  // kept aside from the tokens, for tools that reproduce the source
+  comments: Vec<Token>,
+
+  // where each token was in the source, as byte offsets
+  spans: Vec<( usize, usize )>,
+  errors: Vec<Error>,
   had_error: bool
 }
 
+// the reserved words and the tokens they scan to [ completion offers them too ]
+const KEYWORDS: [( &str, TokenType ); 23] = [
+  ( "and", TokenType::And ), ( "break", TokenType::Break ), ( "catch", TokenType::Catch ),
+  ( "class", TokenType::Class ), ( "continue", TokenType::Continue ), ( "else", TokenType::Else ),
+  ( "false", TokenType::False ), ( "finally", TokenType::Finally ), ( "for", TokenType::For ),
+  ( "fun", TokenType::Fun ), ( "if", TokenType::If ), ( "in", TokenType::In ),
+  ( "nil", TokenType::Nil ), ( "or", TokenType::Or ), ( "print", TokenType::Print ),
+  ( "return", TokenType::Return ), ( "super", TokenType::Super ), ( "this", TokenType::This ),
+  ( "throw", TokenType::Throw ), ( "true", TokenType::True ), ( "try", TokenType::Try ),
+  ( "var", TokenType::Var ), ( "while", TokenType::While )
+];
+
 /////////////////////
 // implementations //
 /////////////////////
@@ -36,6 +57,9 @@ impl Scanner {
       start: 0,
       current: 0,
       line: 1,
+      comments: Vec::new(),
+      spans: Vec::new(),
+      errors: Vec::new(),
       had_error: false
     }
   }
@@ -46,6 +70,7 @@ impl Scanner {
       self.scan_token();
     }
     self.tokens.push( Token{ token_type: TokenType::Eof, lexeme: "[EOF]".into(), line: self.line } );
+    self.spans.push( ( self.src.len(), self.src.len() ) );
     let tokens = self.tokens.clone();
     self.tokens.clear();
     ( tokens, self.had_error )
@@ -56,14 +81,43 @@ impl Scanner {
     self.start = 0;
     self.current = 0;
     self.line = 1;
+    self.comments.clear();
+    self.spans.clear();
+    self.errors.clear();
     self.had_error = false
   }
+  pub fn take_errors( &mut self ) -> Vec<Error> {
+    std::mem::take( &mut self.errors )
+  }
+
+  /// The `//` comments of the last scan, in order.
+  pub fn take_comments( &mut self ) -> Vec<Token> {
+    std::mem::take( &mut self.comments )
+  }
+
+  /// The `tokens` of the last scan as written, each with the whitespace and comments before it.
+  pub fn syntax_tokens( &self, tokens: &[Token] ) -> Vec<SyntaxToken> {
+    let mut end = 0;
+    tokens.iter().zip( &self.spans ).map( | ( token, &( start, stop ) ) | {
+      let leading = split_trivia( &self.src[ end..start ] );
+      end = stop;
+      SyntaxToken {
+        token_type: token.token_type,
+        leading,
+        text: self.src[ start..stop ].into(),
+        line: token.line
+      }
+    } ).collect()
+  }
   fn scan_token( &mut self ) {
     match self.advance() {
       '(' => self.add_token( TokenType::LeftParen, "(" ),
       ')' => self.add_token( TokenType::RightParen, ")" ),
       '{' => self.add_token( TokenType::LeftBrace, "{" ),
       '}' => self.add_token( TokenType::RightBrace, "}" ),
+      '[' => self.add_token( TokenType::LeftBracket, "[" ),
+      ']' => self.add_token( TokenType::RightBracket, "]" ),
+      ':' => self.add_token( TokenType::Colon, ":" ),
       ',' => self.add_token( TokenType::Comma, "," ),
       '.' => self.add_token( TokenType::Dot, "." ),
       '-' => self.add_token( TokenType::Minus, "-" ),
@@ -71,13 +125,19 @@ impl Scanner {
       ';' => self.add_token( TokenType::Semicolon, ";" ),
       '*' => self.add_token( TokenType::Star, "*" ),
       '!' => self.double_char_token( '!', '=', TokenType::BangEqual, TokenType::Bang ),
-      '=' => self.double_char_token( '=', '=', TokenType::EqualEqual, TokenType::Equal ),
+      '=' => if self.advance_if( '>' ) {
+               self.add_token( TokenType::Arrow, "=>" );
+             } else {
+               self.double_char_token( '=', '=', TokenType::EqualEqual, TokenType::Equal );
+             },
       '<' => self.double_char_token( '<', '=', TokenType::LessEqual, TokenType::Less ),
       '>' => self.double_char_token( '>', '=', TokenType::GreaterEqual, TokenType::Greater ),
       '/' => if self.advance_if( '/' ) {
                while self.peek() != '\n' && !self.is_at_end() {
                  self.advance();
                }
+               let text = substring( &self.src, self.start, self.current - self.start ).unwrap();
+               self.comments.push( Token { token_type: TokenType::Comment, lexeme: text.trim_end().into(), line: self.line } );
              } else {
                self.add_token( TokenType::Slash, "/" );
              },
@@ -141,30 +201,16 @@ impl Scanner {
       None => self.add_token( TokenType::Identifier, value.to_owned().as_str() ),
     }
   }
+  pub fn keywords() -> impl Iterator<Item = &'static str> {
+    KEYWORDS.iter().map( | ( keyword, _ ) | *keyword )
+  }
   fn keyword( value: &str ) -> Option<TokenType> {
-    match value {
-      "and" => Some( TokenType::And ),
-      "class" => Some( TokenType::Class ),
-      "else" => Some( TokenType::Else ),
-      "false" => Some( TokenType::False ),
-      "for" => Some( TokenType::For ),
-      "fun" => Some( TokenType::Fun ),
-      "if" => Some( TokenType::If ),
-      "nil" => Some( TokenType::Nil ),
-      "or" => Some( TokenType::Or ),
-      "print" => Some( TokenType::Print ),
-      "return" => Some( TokenType::Return ),
-      "super" => Some( TokenType::Super ),
-      "this" => Some( TokenType::This ),
-      "true" => Some( TokenType::True ),
-      "var" => Some( TokenType::Var ),
-      "while" => Some( TokenType::While ),
-      _ => None
-    }
+    KEYWORDS.iter().find( | ( keyword, _ ) | *keyword == value ).map( | ( _, tt ) | *tt )
   }
   fn advance( &mut self ) -> char {
-    self.current += 1;
-    char_at( &self.src, self.current - 1 ).unwrap()
+    let c = char_at( &self.src, self.current ).unwrap();
+    self.current += c.len_utf8();
+    c
   }
   fn advance_if( &mut self, expected: char ) -> bool {
     if self.is_at_end() {
@@ -173,7 +219,7 @@ impl Scanner {
     if char_at( &self.src, self.current ).unwrap() != expected {
       return false;
     }
-    self.current += 1;
+    self.current += expected.len_utf8();
     true
   }
   fn add_token( &mut self, token_type: TokenType, lexeme: &str ) {
@@ -184,6 +230,7 @@ impl Scanner {
         line: self.line
       }
     );
+    self.spans.push( ( self.start, self.current ) );
   }
   fn peek( &self ) -> char {
     if self.is_at_end() {
@@ -193,17 +240,13 @@ impl Scanner {
     }
   }
   fn peek_next( &self ) -> char {
-    if self.current + 1 >= self.src.len() {
-      '\0'
-    } else {
-      char_at( &self.src, self.current + 1 ).unwrap()
-    }
+    char_at( &self.src, self.current + self.peek().len_utf8() ).unwrap_or( '\0' )
   }
   fn is_at_end( &self ) -> bool {
     self.current >= self.src.len()
   }
   fn emit_error( &mut self, loc: &str, message: &str ) {
-    eprintln!( "[line {}] Error{}: {}", self.line, loc, message );
+    self.errors.push( Error { line: self.line, loc: loc.into(), msg: message.into(), trace: Vec::new() } );
     self.had_error = true;
   }
 }
\ No newline at end of file
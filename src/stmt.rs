@@ -1,97 +1,168 @@
 
 use crate::{expr::Expr, token::Token};
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum Stmt {
   Block( Block ),
+  Break( Break ),
+  Continue( Continue ),
   Expression( Expression ),
+  ForIn( ForIn ),
   Function( Function ),
   If( If ),
   Print( Print ),
   Return( Return ),
+  Throw( Throw ),
+  Try( Try ),
   Var( Var ),
   While( While )
 }
 
 pub trait Visitor<R> {
   fn visit_block_stmt( &mut self, block: &Block ) -> R;
+  fn visit_break_stmt( &mut self, break_: &Break ) -> R;
+  fn visit_continue_stmt( &mut self, continue_: &Continue ) -> R;
   fn visit_expression_stmt( &mut self, expression: &Expression ) -> R;
+  fn visit_for_in_stmt( &mut self, for_in: &ForIn ) -> R;
   fn visit_function_stmt( &mut self, function: &Function ) -> R;
   fn visit_if_stmt( &mut self, if_: &If ) -> R;
   fn visit_print_stmt( &mut self, print: &Print ) -> R;
   fn visit_return_stmt( &mut self, return_: &Return ) -> R;
+  fn visit_throw_stmt( &mut self, throw: &Throw ) -> R;
+  fn visit_try_stmt( &mut self, try_: &Try ) -> R;
   fn visit_var_stmt( &mut self, var: &Var ) -> R;
   fn visit_while_stmt( &mut self, while_: &While ) -> R;
 }
 
 pub trait MutVisitor<R> {
   fn visit_block_stmt_mut( &mut self, block: &mut Block ) -> R;
+  fn visit_break_stmt_mut( &mut self, break_: &mut Break ) -> R;
+  fn visit_continue_stmt_mut( &mut self, continue_: &mut Continue ) -> R;
   fn visit_expression_stmt_mut( &mut self, expression: &mut Expression ) -> R;
+  fn visit_for_in_stmt_mut( &mut self, for_in: &mut ForIn ) -> R;
   fn visit_function_stmt_mut( &mut self, function: &mut Function ) -> R;
   fn visit_if_stmt_mut( &mut self, if_: &mut If ) -> R;
   fn visit_print_stmt_mut( &mut self, print: &mut Print ) -> R;
   fn visit_return_stmt_mut( &mut self, return_: &mut Return ) -> R;
+  fn visit_throw_stmt_mut( &mut self, throw: &mut Throw ) -> R;
+  fn visit_try_stmt_mut( &mut self, try_: &mut Try ) -> R;
   fn visit_var_stmt_mut( &mut self, var: &mut Var ) -> R;
   fn visit_while_stmt_mut( &mut self, while_: &mut While ) -> R;
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Block {
   pub statements: Vec<Stmt>,
-  pub line: u32
+  pub line: u32,
+
+  // the line of the closing brace
+  pub end: u32
+}
+
+#[derive(Clone, Debug)]
+pub struct Break {
+  pub keyword: Token
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
+pub struct Continue {
+  pub keyword: Token
+}
+
+#[derive(Clone, Debug)]
 pub struct Expression {
   pub expression: Expr
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
+pub struct ForIn {
+  pub name: Token,
+  pub keyword: Token,
+  pub iterable: Expr,
+  pub body: Box<Stmt>
+}
+
+#[derive(Clone, Debug)]
 pub struct Function {
   pub name: Token,
   pub params: Vec<Token>,
-  pub body: Vec<Stmt>
+  pub body: Vec<Stmt>,
+
+  // the line the body ends on
+  pub end: u32
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct If {
   pub condition: Expr,
   pub then_branch: Box<Stmt>,
   pub else_branch: Option<Box<Stmt>>
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Print {
   pub expression: Expr
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Return {
   pub keyword: Token,
   pub value: Option<Expr>
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
+pub struct Throw {
+  pub keyword: Token,
+  pub value: Expr
+}
+
+/// `try { ... } catch ( e ) { ... } finally { ... }`, with at least one of the two clauses.
+#[derive(Clone, Debug)]
+pub struct Try {
+  pub keyword: Token,
+  pub body: Block,
+  pub catch: Option<Catch>,
+  pub finally: Option<Block>
+}
+
+#[derive(Clone, Debug)]
+pub struct Catch {
+  pub name: Token,
+  pub body: Block
+}
+
+#[derive(Clone, Debug)]
 pub struct Var {
   pub name: Token,
   pub init: Option<Expr>
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct While {
+
+  // "while", or "for" for a desugared for-loop
+  pub keyword: Token,
   pub condition: Expr,
-  pub body: Box<Stmt>
+  pub body: Box<Stmt>,
+
+  // the increment clause of a desugared for-loop, which `continue` must not skip
+  pub increment: Option<Expr>
 }
 
 impl Stmt {
   pub fn accept<R, V: Visitor<R>>( &self, visitor: &mut V ) -> R {
     match self {
       Stmt::Block( block ) => visitor.visit_block_stmt( block ),
+      Stmt::Break( break_ ) => visitor.visit_break_stmt( break_ ),
+      Stmt::Continue( continue_ ) => visitor.visit_continue_stmt( continue_ ),
       Stmt::Expression( expression ) => visitor.visit_expression_stmt( expression ),
+      Stmt::ForIn( for_in ) => visitor.visit_for_in_stmt( for_in ),
       Stmt::Function( function ) => visitor.visit_function_stmt( function ),
       Stmt::If( if_ ) => visitor.visit_if_stmt( if_ ),
       Stmt::Print( print ) => visitor.visit_print_stmt( print ),
       Stmt::Return( return_ ) => visitor.visit_return_stmt( return_ ),
+      Stmt::Throw( throw ) => visitor.visit_throw_stmt( throw ),
+      Stmt::Try( try_ ) => visitor.visit_try_stmt( try_ ),
       Stmt::Var( var ) => visitor.visit_var_stmt( var ),
       Stmt::While( while_ ) => visitor.visit_while_stmt( while_ ),
     }
@@ -99,11 +170,16 @@ impl Stmt {
   pub fn accept_mut<R, V: MutVisitor<R>>( &mut self, visitor: &mut V ) -> R {
     match self {
       Stmt::Block( block ) => visitor.visit_block_stmt_mut( block ),
+      Stmt::Break( break_ ) => visitor.visit_break_stmt_mut( break_ ),
+      Stmt::Continue( continue_ ) => visitor.visit_continue_stmt_mut( continue_ ),
       Stmt::Expression( expression ) => visitor.visit_expression_stmt_mut( expression ),
+      Stmt::ForIn( for_in ) => visitor.visit_for_in_stmt_mut( for_in ),
       Stmt::Function( function ) => visitor.visit_function_stmt_mut( function ),
       Stmt::If( if_ ) => visitor.visit_if_stmt_mut( if_ ),
       Stmt::Print( print ) => visitor.visit_print_stmt_mut( print ),
       Stmt::Return( return_ ) => visitor.visit_return_stmt_mut( return_ ),
+      Stmt::Throw( throw ) => visitor.visit_throw_stmt_mut( throw ),
+      Stmt::Try( try_ ) => visitor.visit_try_stmt_mut( try_ ),
       Stmt::Var( var ) => visitor.visit_var_stmt_mut( var ),
       Stmt::While( while_ ) => visitor.visit_while_stmt_mut( while_ ),
     }
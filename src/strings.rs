@@ -0,0 +1,309 @@
+//////////////////////////////////
+// private module rlox::strings //
+//////////////////////////////////
+
+
+/////////
+// use //
+/////////
+
+use crate::{byte_code::{NativeFunction, Value, Vm}, error::Error, eval::Eval, interpreter::Interpreter,
+  native::{Arity, Native}, prelude::{parse_number, type_error}, util::RcMut};
+
+
+/////////////////////
+// implementations //
+/////////////////////
+
+/// Looks up the method `name` on Strings.
+///
+/// Strings are sequences of characters (Unicode scalar values), so every length,
+/// index and position below counts characters, not bytes.
+pub fn string_method( name: &str ) -> Option<Native> {
+  let method = match name {
+    "len" => Native::new( name, Arity::Fixed( 0 ), len ),
+    "substring" => Native::new( name, Arity::Fixed( 2 ), substring ),
+    "find" => Native::new( name, Arity::Fixed( 1 ), find ),
+    "replace" => Native::new( name, Arity::Fixed( 2 ), replace ),
+    "split" => Native::new( name, Arity::Fixed( 1 ), split ),
+    "trim" => Native::new( name, Arity::Fixed( 0 ), trim ),
+    "upper" => Native::new( name, Arity::Fixed( 0 ), upper ),
+    "lower" => Native::new( name, Arity::Fixed( 0 ), lower ),
+    "starts_with" => Native::new( name, Arity::Fixed( 1 ), starts_with ),
+    "ends_with" => Native::new( name, Arity::Fixed( 1 ), ends_with ),
+    "repeat" => Native::new( name, Arity::Fixed( 1 ), repeat ),
+    "to_number" => Native::new( name, Arity::Fixed( 0 ), to_number ),
+    _ => return None
+  };
+  Some( method )
+}
+
+/// Evaluates `s[ index ]` as a one-character String.
+pub fn char_at( s: &str, index: &Eval ) -> Result<Eval, Error> {
+  Ok( Eval::StringLiteral( nth_char( s, number( index ), index.repr() )? ) )
+}
+
+/// The byte code counterpart of `string_method`.
+pub fn vm_string_method( name: &str ) -> Option<NativeFunction> {
+  let method = match name {
+    "len" => NativeFunction::new( name, Arity::Fixed( 0 ), vm_len ),
+    "substring" => NativeFunction::new( name, Arity::Fixed( 2 ), vm_substring ),
+    "find" => NativeFunction::new( name, Arity::Fixed( 1 ), vm_find ),
+    "replace" => NativeFunction::new( name, Arity::Fixed( 2 ), vm_replace ),
+    "split" => NativeFunction::new( name, Arity::Fixed( 1 ), vm_split ),
+    "trim" => NativeFunction::new( name, Arity::Fixed( 0 ), vm_trim ),
+    "upper" => NativeFunction::new( name, Arity::Fixed( 0 ), vm_upper ),
+    "lower" => NativeFunction::new( name, Arity::Fixed( 0 ), vm_lower ),
+    "starts_with" => NativeFunction::new( name, Arity::Fixed( 1 ), vm_starts_with ),
+    "ends_with" => NativeFunction::new( name, Arity::Fixed( 1 ), vm_ends_with ),
+    "repeat" => NativeFunction::new( name, Arity::Fixed( 1 ), vm_repeat ),
+    "to_number" => NativeFunction::new( name, Arity::Fixed( 0 ), vm_to_number ),
+    _ => return None
+  };
+  Some( method )
+}
+
+/// The byte code counterpart of `char_at`.
+pub fn vm_char_at( s: &str, index: &Value ) -> Result<Value, Error> {
+  Ok( Value::String( nth_char( s, vm_number( index ), index.repr() )? ) )
+}
+
+
+/////////////////////////////////
+// shared by both the backends //
+/////////////////////////////////
+
+/// Checks that `index` is a whole, non-negative Number, reporting what was `found` otherwise.
+fn whole( what: &str, index: Option<f64>, found: &str ) -> Result<f64, Error> {
+  match index {
+    Some( x ) if x.fract() == 0.0 && x >= 0.0 => Ok( x ),
+    _ => Err( Error::from_msg( format!( "{} must be a whole, non-negative Number, but found {}.", what, found ) ) )
+  }
+}
+
+/// Checks that `index` is a whole number within `0..len`, or `0..=len` when `inclusive`.
+///
+/// The check happens before the conversion to `usize`, so an error quotes the number as written.
+fn position( what: &str, index: Option<f64>, found: String, len: usize, inclusive: bool ) -> Result<usize, Error> {
+  let x = whole( what, index, &found )?;
+  if x > len as f64 || ( x == len as f64 && !inclusive ) {
+    return Err( Error::from_msg( format!( "{} {} is out of range for a String of length {}.", what, found, len ) ) );
+  }
+  Ok( x as usize )
+}
+
+fn nth_char( s: &str, index: Option<f64>, found: String ) -> Result<String, Error> {
+  let i = position( "String index", index, found, s.chars().count(), false )?;
+  Ok( s.chars().nth( i ).unwrap().to_string() )
+}
+
+fn substring_of( s: &str, start: ( Option<f64>, String ), end: ( Option<f64>, String ) ) -> Result<String, Error> {
+  let count = s.chars().count();
+  let start = position( "substring() start", start.0, start.1, count, true )?;
+  let end = position( "substring() end", end.0, end.1, count, true )?;
+  if start > end {
+    return Err( Error::from_msg( format!( "substring() range {}..{} runs backwards.", start, end ) ) );
+  }
+  Ok( s.chars().skip( start ).take( end - start ).collect() )
+}
+
+fn find_in( s: &str, needle: &str ) -> Option<f64> {
+  s.find( needle ).map( | byte | s[ ..byte ].chars().count() as f64 )
+}
+
+fn split_by( s: &str, separator: &str ) -> Vec<String> {
+
+  // an empty separator splits between characters
+  if separator.is_empty() {
+    s.chars().map( | c | c.to_string() ).collect()
+  } else {
+    s.split( separator ).map( | piece | piece.to_string() ).collect()
+  }
+}
+
+/// The number of times `repeat()` was asked to repeat its receiver.
+///
+/// Counts past usize::MAX saturate, which `repeat_size` still refuses unless the receiver is empty.
+fn repeat_count( count: Option<f64>, found: &str ) -> Result<usize, Error> {
+  Ok( whole( "repeat() count", count, found )? as usize )
+}
+
+/// The bytes `s` repeated `count` times takes, refusing a String too long to exist at all.
+fn repeat_size( s: &str, count: usize, found: &str ) -> Result<usize, Error> {
+  match s.len().checked_mul( count ) {
+    Some( bytes ) if bytes <= isize::MAX as usize => Ok( bytes ),
+    _ => Err( Error::from_msg( format!( "repeat() count {} makes a String too long.", found ) ) )
+  }
+}
+
+
+///////////////////////////
+// tree-walk interpreter //
+///////////////////////////
+
+fn receiver( args: &[Eval] ) -> &str {
+  match &args[ 0 ] {
+    Eval::StringLiteral( s ) => s,
+    _ => unreachable!( "Internal error: String methods are only bound to Strings." )
+  }
+}
+
+fn string<'a>( fun: &str, arg: &'a Eval ) -> Result<&'a str, Error> {
+  match arg {
+    Eval::StringLiteral( s ) => Ok( s ),
+    other => Err( type_error( fun, "String", other.get_type_name() ) )
+  }
+}
+
+fn number( arg: &Eval ) -> Option<f64> {
+  match arg {
+    Eval::Number( x ) => Some( *x ),
+    _ => None
+  }
+}
+
+fn len( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  Ok( Eval::Number( receiver( args ).chars().count() as f64 ) )
+}
+
+fn substring( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  let start = ( number( &args[ 1 ] ), args[ 1 ].repr() );
+  let end = ( number( &args[ 2 ] ), args[ 2 ].repr() );
+  Ok( Eval::StringLiteral( substring_of( receiver( args ), start, end )? ) )
+}
+
+fn find( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  let needle = string( "find", &args[ 1 ] )?;
+  Ok( find_in( receiver( args ), needle ).map_or( Eval::Nil, Eval::Number ) )
+}
+
+fn replace( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  let from = string( "replace", &args[ 1 ] )?;
+  let to = string( "replace", &args[ 2 ] )?;
+  Ok( Eval::StringLiteral( receiver( args ).replace( from, to ) ) )
+}
+
+fn split( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  let separator = string( "split", &args[ 1 ] )?;
+  let pieces = split_by( receiver( args ), separator ).into_iter().map( Eval::StringLiteral ).collect();
+  Ok( Eval::List( RcMut::new( pieces ) ) )
+}
+
+fn trim( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  Ok( Eval::StringLiteral( receiver( args ).trim().to_string() ) )
+}
+
+fn upper( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  Ok( Eval::StringLiteral( receiver( args ).to_uppercase() ) )
+}
+
+fn lower( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  Ok( Eval::StringLiteral( receiver( args ).to_lowercase() ) )
+}
+
+fn starts_with( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  let prefix = string( "starts_with", &args[ 1 ] )?;
+  Ok( Eval::Bool( receiver( args ).starts_with( prefix ) ) )
+}
+
+fn ends_with( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  let suffix = string( "ends_with", &args[ 1 ] )?;
+  Ok( Eval::Bool( receiver( args ).ends_with( suffix ) ) )
+}
+
+fn repeat( interpreter: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  let count = repeat_count( number( &args[ 1 ] ), &args[ 1 ].repr() )?;
+  interpreter.spend( count as u64 )?;
+  interpreter.make_room( repeat_size( receiver( args ), count, &args[ 1 ].repr() )? )?;
+  Ok( Eval::StringLiteral( receiver( args ).repeat( count ) ) )
+}
+
+fn to_number( _: &mut Interpreter, args: &[Eval] ) -> Result<Eval, Error> {
+  Ok( Eval::Number( parse_number( "to_number", receiver( args ) )? ) )
+}
+
+
+///////////////
+// byte code //
+///////////////
+
+fn vm_receiver( args: &[Value] ) -> &str {
+  match &args[ 0 ] {
+    Value::String( s ) => s,
+    _ => unreachable!( "Internal error: String methods are only bound to Strings." )
+  }
+}
+
+fn vm_string<'a>( fun: &str, arg: &'a Value ) -> Result<&'a str, Error> {
+  match arg {
+    Value::String( s ) => Ok( s ),
+    other => Err( type_error( fun, "String", other.get_type_name() ) )
+  }
+}
+
+fn vm_number( arg: &Value ) -> Option<f64> {
+  match arg {
+    Value::Number( x ) => Some( *x ),
+    _ => None
+  }
+}
+
+fn vm_len( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  Ok( Value::Number( vm_receiver( args ).chars().count() as f64 ) )
+}
+
+fn vm_substring( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  let start = ( vm_number( &args[ 1 ] ), args[ 1 ].repr() );
+  let end = ( vm_number( &args[ 2 ] ), args[ 2 ].repr() );
+  Ok( Value::String( substring_of( vm_receiver( args ), start, end )? ) )
+}
+
+fn vm_find( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  let needle = vm_string( "find", &args[ 1 ] )?;
+  Ok( find_in( vm_receiver( args ), needle ).map_or( Value::Nil, Value::Number ) )
+}
+
+fn vm_replace( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  let from = vm_string( "replace", &args[ 1 ] )?;
+  let to = vm_string( "replace", &args[ 2 ] )?;
+  Ok( Value::String( vm_receiver( args ).replace( from, to ) ) )
+}
+
+fn vm_split( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  let separator = vm_string( "split", &args[ 1 ] )?;
+  let pieces = split_by( vm_receiver( args ), separator ).into_iter().map( Value::String ).collect();
+  Ok( Value::List( RcMut::new( pieces ) ) )
+}
+
+fn vm_trim( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  Ok( Value::String( vm_receiver( args ).trim().to_string() ) )
+}
+
+fn vm_upper( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  Ok( Value::String( vm_receiver( args ).to_uppercase() ) )
+}
+
+fn vm_lower( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  Ok( Value::String( vm_receiver( args ).to_lowercase() ) )
+}
+
+fn vm_starts_with( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  let prefix = vm_string( "starts_with", &args[ 1 ] )?;
+  Ok( Value::Boolean( vm_receiver( args ).starts_with( prefix ) ) )
+}
+
+fn vm_ends_with( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  let suffix = vm_string( "ends_with", &args[ 1 ] )?;
+  Ok( Value::Boolean( vm_receiver( args ).ends_with( suffix ) ) )
+}
+
+fn vm_repeat( vm: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  let count = repeat_count( vm_number( &args[ 1 ] ), &args[ 1 ].repr() )?;
+  vm.spend( count as u64 )?;
+  vm.make_room( repeat_size( vm_receiver( args ), count, &args[ 1 ].repr() )? )?;
+  Ok( Value::String( vm_receiver( args ).repeat( count ) ) )
+}
+
+fn vm_to_number( _: &mut Vm, args: &[Value] ) -> Result<Value, Error> {
+  Ok( Value::Number( parse_number( "to_number", vm_receiver( args ) )? ) )
+}
@@ -0,0 +1,299 @@
+//////////////////////////////////////
+// private module rlox::test_runner //
+//////////////////////////////////////
+
+
+/////////
+// use //
+/////////
+
+use std::{fs, io::{self, Write}, path::{Path, PathBuf}};
+
+use crate::{error::Error, execute, front_end, global_names, script_interpreter, stmt::Stmt, util::RcMut,
+  Backend, Limits, Status, MAX_DEPTH};
+
+
+//////////////////
+// declarations //
+//////////////////
+
+/// What a test script's comments say running it should do, after the test suite
+/// of Crafting Interpreters:
+///
+/// - `// expect: value` for each line the script prints, in order;
+/// - `// expect runtime error: msg` for the runtime error it stops on, at that line;
+/// - `// expect trace: [line N] in f()` for each line of that error's stack trace, in order;
+/// - `// [line N] Error ...` or `// Error ...` [ at that line ] for each compile error.
+///
+/// `// max depth: N`, `// budget: N` and `// max memory: N` run it under those limits.
+struct Expectations {
+
+  // what should be printed, with the line of the comment expecting it
+  output: Vec<( String, u32 )>,
+
+  // as reported, e.g. "[line 3] Error at 'x': Undeclared symbol."
+  compile_errors: Vec<String>,
+  runtime_error: Option<( String, u32 )>,
+  trace: Vec<String>,
+  limits: Limits
+}
+
+/// A writer that keeps what it's given, so a script's output can be checked afterwards.
+#[derive(Clone)]
+struct Capture {
+  buffer: RcMut<Vec<u8>>
+}
+
+
+/////////////////////
+// implementations //
+/////////////////////
+
+impl Expectations {
+  fn parse( src: &str ) -> Expectations {
+    let mut expectations = Expectations {
+      output: Vec::new(),
+      compile_errors: Vec::new(),
+      runtime_error: None,
+      trace: Vec::new(),
+      limits: Limits::default()
+    };
+    for ( idx, text ) in src.lines().enumerate() {
+      let line = idx as u32 + 1;
+      if let Some( value ) = after( text, "// expect: " ) {
+        expectations.output.push( ( value.to_string(), line ) );
+      } else if let Some( msg ) = after( text, "// expect runtime error: " ) {
+        expectations.runtime_error = Some( ( msg.to_string(), line ) );
+      } else if let Some( entry ) = after( text, "// expect trace: " ) {
+        expectations.trace.push( entry.to_string() );
+      } else if let Some( error ) = after( text, "// [line " ) {
+        expectations.compile_errors.push( format!( "[line {}", error ) );
+      } else if let Some( error ) = after( text, "// Error" ) {
+        expectations.compile_errors.push( format!( "[line {}] Error{}", line, error ) );
+      } else if let Some( depth ) = after( text, "// max depth: " ).and_then( | n | n.parse().ok() ) {
+        expectations.limits.max_depth = usize::min( depth, MAX_DEPTH );
+      } else if let Some( budget ) = after( text, "// budget: " ).and_then( | n | n.parse().ok() ) {
+        expectations.limits.budget = Some( budget );
+      } else if let Some( memory ) = after( text, "// max memory: " ).and_then( | n | n.parse().ok() ) {
+        expectations.limits.max_memory = Some( memory );
+      }
+    }
+    expectations
+  }
+}
+
+impl Capture {
+  fn new() -> Capture {
+    Capture {
+      buffer: RcMut::new( Vec::new() )
+    }
+  }
+  fn text( &self ) -> String {
+    String::from_utf8_lossy( &self.buffer.view() ).into_owned()
+  }
+}
+
+impl Write for Capture {
+  fn write( &mut self, buf: &[u8] ) -> io::Result<usize> {
+    self.buffer.view_mut().extend_from_slice( buf );
+    Ok( buf.len() )
+  }
+  fn flush( &mut self ) -> io::Result<()> {
+    Ok( () )
+  }
+}
+
+/// Runs every `.lox` script under `dir`, printing PASS or FAIL for each, and for a
+/// script whose output is off, a diff of the output expected against what it printed.
+///
+/// A script's top-level `test_*` functions are unit tests as well: each is called on
+/// a fresh run of the script, so no test sees what another did, and passes if it
+/// returns without error.
+pub fn run_tests( dir: &str, backend: Backend ) -> Status {
+  let mut paths = Vec::new();
+  if let Err( e ) = collect_scripts( Path::new( dir ), &mut paths ) {
+    eprintln!( "Error reading directory '{}': {}", dir, e );
+    return Status::IoError;
+  }
+  paths.sort();
+  let ( mut total, mut failed ) = ( paths.len(), 0 );
+  for path in &paths {
+    let src = match fs::read_to_string( path ) {
+      Ok( src ) => src,
+      Err( e ) => {
+        eprintln!( "Error reading file '{}': {}", path.display(), e );
+        return Status::IoError;
+      }
+    };
+    let units = unit_tests( &src );
+    let failures = run_test( src.clone(), backend );
+    if failures.is_empty() {
+      println!( "PASS {}", path.display() );
+    } else {
+      failed += 1;
+      println!( "FAIL {}", path.display() );
+      for failure in failures {
+        for line in failure.lines() {
+          println!( "     {}", line );
+        }
+      }
+    }
+    for name in units {
+      total += 1;
+      let errors = run_unit_test( &src, &name, backend );
+      if errors.is_empty() {
+        println!( "PASS {} > {}", path.display(), name );
+      } else {
+        failed += 1;
+        println!( "FAIL {} > {}", path.display(), name );
+        for error in errors {
+          for line in error.lines() {
+            println!( "     {}", line );
+          }
+        }
+      }
+    }
+  }
+  if failed == 0 {
+    println!( "All {} tests passed.", total );
+    Status::Success
+  } else {
+    println!( "{} tests passed. {} tests failed.", total - failed, failed );
+    Status::TestFailure
+  }
+}
+
+/// Runs one script, describing each way it fell short of its expectations.
+fn run_test( src: String, backend: Backend ) -> Vec<String> {
+  let expected = Expectations::parse( &src );
+  let capture = Capture::new();
+  let outcome = execute( src, &[], backend, expected.limits, false, false, Box::new( capture.clone() ) );
+  let mut failures = Vec::new();
+
+  // diagnostics
+  let reported: Vec<String> = outcome.errors.iter().map( headline ).collect();
+  if !expected.compile_errors.is_empty() {
+    if outcome.status != Status::CompileError {
+      failures.push( format!( "Expected compile errors, but the script {}.", match outcome.status {
+        Status::RuntimeError => "failed at runtime",
+        _ => "ran"
+      } ) );
+    }
+    for error in &expected.compile_errors {
+      if !reported.contains( error ) {
+        failures.push( format!( "Missing expected error: {}", error ) );
+      }
+    }
+    for error in &reported {
+      if !expected.compile_errors.contains( error ) {
+        failures.push( format!( "Unexpected error: {}", error ) );
+      }
+    }
+  } else if let Some( ( msg, line ) ) = &expected.runtime_error {
+    match outcome.errors.first() {
+      Some( error ) if outcome.status == Status::RuntimeError => {
+        if error.msg != *msg {
+          failures.push( format!( "Expected runtime error '{}' and got '{}'.", msg, error.msg ) );
+        } else if error.line != *line {
+          failures.push( format!( "Expected runtime error on line {} but was on line {}.", line, error.line ) );
+        }
+        let entries = trace( error );
+        let actual: Vec<&str> = entries.iter().map( | entry | entry.as_str() ).collect();
+        let wanted: Vec<&str> = expected.trace.iter().map( | entry | entry.as_str() ).collect();
+        if !wanted.is_empty() && actual != wanted {
+          failures.push( format!( "Stack trace differs [ - expected, + actual ]:\n{}", diff( &wanted, &actual ) ) );
+        }
+      },
+      _ => {
+        failures.push( format!( "Expected runtime error '{}' and got none.", msg ) );
+        for error in &reported {
+          failures.push( format!( "Unexpected error: {}", error ) );
+        }
+      }
+    }
+  } else {
+    for error in &reported {
+      failures.push( format!( "Unexpected error: {}", error ) );
+    }
+  }
+
+  // output
+  let output = capture.text();
+  let actual: Vec<&str> = output.lines().collect();
+  let wanted: Vec<&str> = expected.output.iter().map( | ( value, _ ) | value.as_str() ).collect();
+  if actual != wanted {
+    failures.push( format!( "Output differs [ - expected, + actual ]:\n{}", diff( &wanted, &actual ) ) );
+  }
+  failures
+}
+
+// the two outputs line by line, with matching lines once and differing ones as a pair
+fn diff( expected: &[&str], actual: &[&str] ) -> String {
+  let mut lines = Vec::new();
+  for idx in 0..usize::max( expected.len(), actual.len() ) {
+    match ( expected.get( idx ), actual.get( idx ) ) {
+      ( Some( wanted ), Some( got ) ) if wanted == got => lines.push( format!( "  {}", got ) ),
+      ( wanted, got ) => {
+        if let Some( wanted ) = wanted {
+          lines.push( format!( "- {}", wanted ) );
+        }
+        if let Some( got ) = got {
+          lines.push( format!( "+ {}", got ) );
+        }
+      }
+    }
+  }
+  lines.join( "\n" )
+}
+
+/// The names of the `test_*` functions declared at the top level of `src`.
+fn unit_tests( src: &str ) -> Vec<String> {
+  match front_end( src.into(), global_names( &script_interpreter( &[] ) ), false ) {
+    Ok( stmts ) => stmts.iter().filter_map( | stmt | match stmt {
+      Stmt::Function( function ) if function.name.lexeme.starts_with( "test_" ) => Some( function.name.lexeme.clone() ),
+      _ => None
+    } ).collect(),
+
+    // the script's own run reports these
+    Err( _ ) => Vec::new()
+  }
+}
+
+/// Runs the top level of `src` [ its output unchecked, as the script's own test checks it ]
+/// and then calls its function `name`, returning the errors that stopped it.
+fn run_unit_test( src: &str, name: &str, backend: Backend ) -> Vec<String> {
+  let limits = Expectations::parse( src ).limits;
+  let outcome = execute( src.into(), &[], backend, limits, false, false, Box::new( io::sink() ) );
+  match outcome.session {
+    Some( mut session ) => session.call( name ).iter().map( | error | error.to_string() ).collect(),
+    None => vec![ "The script didn't compile.".into() ]
+  }
+}
+
+/// Gathers the `.lox` files under `dir`, descending into subdirectories.
+fn collect_scripts( dir: &Path, paths: &mut Vec<PathBuf> ) -> io::Result<()> {
+  for entry in fs::read_dir( dir )? {
+    let path = entry?.path();
+    if path.is_dir() {
+      collect_scripts( &path, paths )?;
+    } else if path.extension().is_some_and( | ext | ext == "lox" ) {
+      paths.push( path );
+    }
+  }
+  Ok( () )
+}
+
+/// The text following `marker` in `line`, if it has one.
+fn after<'a>( line: &'a str, marker: &str ) -> Option<&'a str> {
+  line.find( marker ).map( | idx | line[ idx + marker.len().. ].trim_end() )
+}
+
+// the lines of an error's stack trace as printed, without the heading
+fn trace( error: &Error ) -> Vec<String> {
+  error.to_string().lines().skip( 2 ).map( | line | line.trim().to_string() ).collect()
+}
+
+// the first line of an error, without any stack trace
+fn headline( error: &Error ) -> String {
+  format!( "[line {}] Error{}: {}", error.line, error.loc, error.msg )
+}
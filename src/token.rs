@@ -1,22 +1,25 @@
 
 
-#[derive(Clone, Copy, PartialEq, Hash, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Hash, Eq)]
 pub enum TokenType {
-  LeftParen, RightParen, LeftBrace, RightBrace,
-  Comma, Dot, Minus, Plus, Semicolon, Slash, Star,
+  LeftParen, RightParen, LeftBrace, RightBrace, LeftBracket, RightBracket,
+  Colon, Comma, Dot, Minus, Plus, Semicolon, Slash, Star,
 
-  Bang, BangEqual, Equal, EqualEqual,
+  Bang, BangEqual, Equal, EqualEqual, Arrow,
   Greater, GreaterEqual, Less, LessEqual,
 
   Identifier, String, Number,
 
-  And, Class, Else, False, Fun, For, If, Nil, Or,
-  Print, Return, Super, This, True, Var, While,
+  And, Break, Class, Continue, Else, False, Fun, For, If, In, Nil, Or,
+  Print, Return, Super, This, Throw, True, Try, Var, While,
+  Catch, Finally,
+
+  Comment,
 
   Eof
 }
 
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct Token {
   pub token_type: TokenType,
   pub lexeme: String,
@@ -32,6 +35,9 @@ impl TokenType {
       TokenType::RightParen => ")",
       TokenType::LeftBrace => "{",
       TokenType::RightBrace => "}",
+      TokenType::LeftBracket => "[",
+      TokenType::RightBracket => "]",
+      TokenType::Colon => ":",
       TokenType::Comma => ",",
       TokenType::Dot => ".",
       TokenType::Minus => "-",
@@ -43,6 +49,7 @@ impl TokenType {
       TokenType::BangEqual => "!=",
       TokenType::Equal => "=",
       TokenType::EqualEqual => "==",
+      TokenType::Arrow => "=>",
       TokenType::Greater => ">",
       TokenType::GreaterEqual => ">=",
       TokenType::Less => "<",
@@ -51,21 +58,29 @@ impl TokenType {
       TokenType::String => "[string]",
       TokenType::Number => "[number]",
       TokenType::And => "and",
+      TokenType::Break => "break",
       TokenType::Class => "class",
+      TokenType::Continue => "continue",
       TokenType::Else => "else",
       TokenType::False => "false",
       TokenType::Fun => "fun",
       TokenType::For => "for",
       TokenType::If => "if",
+      TokenType::In => "in",
       TokenType::Nil => "nil",
       TokenType::Or => "or",
       TokenType::Print => "print",
       TokenType::Return => "return",
       TokenType::Super => "super",
       TokenType::This => "this",
+      TokenType::Throw => "throw",
+      TokenType::Try => "try",
+      TokenType::Catch => "catch",
+      TokenType::Finally => "finally",
       TokenType::True => "true",
       TokenType::Var => "var",
       TokenType::While => "while",
+      TokenType::Comment => "[comment]",
       TokenType::Eof => "[EOF]",
     }
   }
@@ -7,7 +7,8 @@
 // use //
 /////////
 
-use std::{cell::{Ref, RefCell, RefMut}, collections::HashMap, hash::{DefaultHasher, Hash, Hasher}, rc::Rc};
+use std::{cell::{Ref, RefCell, RefMut}, collections::HashMap, hash::{DefaultHasher, Hash, Hasher}, rc::Rc,
+  sync::{atomic::{AtomicBool, Ordering}, Arc}};
 
 
 ///////////////////
@@ -31,10 +32,7 @@ pub fn substring<'a>( s: &'a str, start: usize, len: usize ) -> Option<&'a str>
 }
 
 pub fn char_at( s: &str, idx: usize ) -> Option<char> {
-  for x in s.char_indices().filter( | ( i, _ ) | *i == idx ).take( 1 ) {
-      return Some( x.1 );
-  }
-  None
+  s.get( idx.. )?.chars().next()
 }
 
 pub fn ifte<T>( condition: bool, true_val: T, false_val: T ) -> T {
@@ -231,6 +229,17 @@ impl<T> Stack<T> {
   pub fn push( &mut self, value: T ) {
     self.vec.push( value );
   }
+
+  // absolute access, counting up from the bottom of the stack
+  pub fn get( &self, idx: usize ) -> &T {
+    self.vec.get( idx ).unwrap()
+  }
+  pub fn get_mut( &mut self, idx: usize ) -> &mut T {
+    self.vec.get_mut( idx ).unwrap()
+  }
+  pub fn truncate( &mut self, depth: usize ) {
+    self.vec.truncate( depth );
+  }
 }
 
 
@@ -255,6 +264,124 @@ impl<T> RcMut<T> {
   pub fn view_mut( &mut self ) -> RefMut<T> {
     self.shared_ptr.as_ref().borrow_mut()
   }
+
+  /// Identifies the shared value, so a walk over values can visit each one once.
+  pub fn addr( &self ) -> usize {
+    Rc::as_ptr( &self.shared_ptr ) as *const () as usize
+  }
+}
+
+
+//////////
+// Fuel //
+//////////
+
+/// An execution budget spent one unit per step [ and by natives, per unit of work ],
+/// and a flag a host can raise from another thread to stop execution at the next step.
+pub struct Fuel {
+  limit: Option<u64>,
+  spent: u64,
+  interrupt: Arc<AtomicBool>,
+
+  // why the run had to stop, once it has
+  stopped: Option<&'static str>
+}
+
+impl Fuel {
+  pub fn new() -> Fuel {
+    Fuel {
+      limit: None,
+      spent: 0,
+      interrupt: Arc::new( AtomicBool::new( false ) ),
+      stopped: None
+    }
+  }
+  pub fn set_limit( &mut self, limit: Option<u64> ) {
+    self.limit = limit;
+  }
+  pub fn interrupt_handle( &self ) -> Arc<AtomicBool> {
+    self.interrupt.clone()
+  }
+
+  // each run starts with the full budget, and no interrupt left over from the last one
+  pub fn refill( &mut self ) {
+    self.spent = 0;
+    self.stopped = None;
+    self.interrupt.store( false, Ordering::Relaxed );
+  }
+
+  /// Spends `steps` steps, returning why execution must stop if it can't go on.
+  ///
+  /// Natives spend in proportion to the work they are about to do, before doing it.
+  pub fn burn( &mut self, steps: u64 ) -> Option<&'static str> {
+    if self.interrupt.swap( false, Ordering::Relaxed ) {
+      self.stopped = Some( "Execution interrupted." );
+    }
+    self.spent = self.spent.saturating_add( steps );
+    if self.limit.is_some_and( | limit | self.spent > limit ) {
+      self.stopped = self.stopped.or( Some( "Execution budget exhausted." ) );
+    }
+    self.stopped
+  }
+
+  /// Whether the run has had to stop, so that an error on the way out isn't caught.
+  pub fn stopped( &self ) -> bool {
+    self.stopped.is_some()
+  }
+}
+
+
+////////////
+// Memory //
+////////////
+
+/// The most a run may hold when no cap is set, so a native asked for an absurd
+/// size fails with an error instead of taking the process down.
+pub const MEMORY_CEILING: usize = u32::MAX as usize;
+
+/// A cap on the bytes a run holds for values and bindings.
+///
+/// Charges add up until one doesn't fit. The caller then measures what the run can
+/// still reach and settles the count to that, much as a collector would, and tries again.
+pub struct Memory {
+  limit: Option<usize>,
+  allocated: usize
+}
+
+impl Memory {
+  pub fn new() -> Memory {
+    Memory {
+      limit: None,
+      allocated: 0
+    }
+  }
+  pub fn set_limit( &mut self, limit: Option<usize> ) {
+    self.limit = limit;
+  }
+
+  // each run starts with nothing allocated
+  pub fn reset( &mut self ) {
+    self.allocated = 0;
+  }
+
+  /// How many more bytes fit under the cap [ or under `MEMORY_CEILING`, without one ].
+  pub fn room( &self ) -> usize {
+    self.limit.unwrap_or( MEMORY_CEILING ).saturating_sub( self.allocated )
+  }
+
+  /// Records `bytes` as allocated, or returns false if they don't fit.
+  pub fn charge( &mut self, bytes: usize ) -> bool {
+    if bytes > self.room() {
+      return false;
+    }
+    self.allocated += bytes;
+    true
+  }
+
+  /// Restarts the count from the `live` bytes the run can still reach.
+  pub fn settle( &mut self, live: usize ) {
+    self.allocated = live;
+  }
 }
 
 
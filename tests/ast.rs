@@ -0,0 +1,34 @@
+use std::process::Command;
+
+// a script touching most nodes, and what `rlox ast` prints for it
+const SCRIPT: &str = concat!( env!( "CARGO_MANIFEST_DIR" ), "/tests/scripts/shared/ast/tree.lox" );
+const SEXP: &str = include_str!( "scripts/shared/ast/tree.sexp" );
+const JSON: &str = include_str!( "scripts/shared/ast/tree.json" );
+
+fn ast( args: &[&str] ) -> String {
+  let output = Command::new( env!( "CARGO_BIN_EXE_rlox" ) ).arg( "ast" ).args( args ).arg( SCRIPT ).output().unwrap();
+  assert_eq!( output.status.code(), Some( 0 ), "{}", String::from_utf8_lossy( &output.stderr ) );
+  String::from_utf8_lossy( &output.stdout ).into_owned()
+}
+
+#[test]
+fn s_expressions_match() {
+  assert_eq!( ast( &[] ), SEXP );
+}
+
+#[test]
+fn json_matches() {
+  assert_eq!( ast( &[ "--json" ] ), JSON );
+}
+
+#[test]
+fn every_json_statement_has_a_line() {
+  let statements = [ "Var", "Function", "Return", "Block", "Expression", "Print", "If", "While", "Continue", "Break", "Try", "Throw" ];
+  let json = ast( &[ "--json" ] );
+  let lines: Vec<&str> = json.lines().collect();
+  for ( idx, text ) in lines.iter().enumerate() {
+    if statements.iter().any( | kind | text.trim() == format!( "\"type\": \"{}\",", kind ) ) {
+      assert!( lines[ idx + 1.. ].iter().take( 3 ).any( | next | next.trim_start().starts_with( "\"line\"" ) ), "no line for {}", text.trim() );
+    }
+  }
+}
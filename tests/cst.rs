@@ -0,0 +1,55 @@
+use rlox::{syntax_tree, NodeKind, SyntaxNode};
+
+const SCRIPT: &str = "// a comment before anything
+fun add(a, b) {   // trailing
+  return a+b;
+}
+var total = add(1, 2);  print total;
+for (var i = 0; i < 2; i = i + 1) { print [i, {\"k\": i}]; }
+
+";
+
+// the kinds of `node` and everything beneath it, depth first
+fn kinds( node: &SyntaxNode ) -> Vec<NodeKind> {
+  let mut found = vec![ node.kind ];
+  for child in node.nodes() {
+    found.extend( kinds( child ) );
+  }
+  found
+}
+
+#[test]
+fn tree_prints_the_source_back() {
+  let ( tree, errors ) = syntax_tree( SCRIPT );
+  assert!( errors.is_empty() );
+  assert_eq!( tree.to_string(), SCRIPT );
+  let statements: Vec<NodeKind> = tree.nodes().map( | node | node.kind ).collect();
+  assert_eq!( statements, vec![ NodeKind::FunDecl, NodeKind::VarDecl, NodeKind::PrintStmt, NodeKind::ForStmt ] );
+  assert!( kinds( &tree ).contains( &NodeKind::MapEntry ) );
+}
+
+#[test]
+fn broken_source_still_gets_a_tree() {
+  for src in [
+    "var a = ;\nprint 1;\n",
+    "fun f( {\n  print 2;\n",
+    "print (1 + ;\n// done\n",
+    "var b = \"unterminated\nprint b;\n",
+    "var c = 1 @ 2;\nprint c;\n"
+  ] {
+    let ( tree, errors ) = syntax_tree( src );
+    assert!( !errors.is_empty(), "no errors for {:?}", src );
+    assert_eq!( tree.to_string(), src );
+    assert_eq!( tree.kind, NodeKind::Script );
+  }
+}
+
+#[test]
+fn parse_errors_close_the_broken_node_and_keep_the_rest_as_tokens() {
+  let ( tree, _ ) = syntax_tree( "print 1;\nvar a = ;\nprint 2;\n" );
+  let kinds: Vec<NodeKind> = tree.nodes().map( | node | node.kind ).collect();
+  assert_eq!( kinds, vec![ NodeKind::PrintStmt, NodeKind::VarDecl ] );
+  assert_eq!( format!( "{:?}", kinds[ 1 ] ), "VarDecl" );
+  let rest: Vec<String> = tree.children.iter().skip( 2 ).map( | child | child.to_string() ).collect();
+  assert_eq!( rest.concat(), " ;\nprint 2;\n" );
+}
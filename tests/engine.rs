@@ -0,0 +1,51 @@
+use rlox::Engine;
+
+// what `src` evaluates to, as `print` would show it
+fn eval( engine: &mut Engine, src: &str ) -> String {
+  match engine.eval( src ) {
+    Ok( value ) => value.to_string(),
+    Err( diagnostics ) => panic!( "{}", diagnostics )
+  }
+}
+
+#[test]
+fn globals_can_be_redeclared_across_evaluations() {
+  let mut engine = Engine::new();
+  eval( &mut engine, "var x = 1;" );
+  eval( &mut engine, "var x = \"two\";" );
+  assert_eq!( eval( &mut engine, "x;" ), "two" );
+}
+
+#[test]
+fn functions_can_be_redefined_across_evaluations() {
+  let mut engine = Engine::new();
+  eval( &mut engine, "fun f() { return 1; }" );
+  eval( &mut engine, "fun f() { return 2; }" );
+  assert_eq!( eval( &mut engine, "f();" ), "2" );
+}
+
+#[test]
+fn redeclaring_within_one_evaluation_is_still_an_error() {
+  let mut engine = Engine::new();
+  assert!( engine.eval( "{ var y = 1; var y = 2; }" ).is_err() );
+}
+
+#[test]
+fn runaway_recursion_fails_on_a_default_thread_stack() {
+
+  // the test harness runs this on a thread with Rust's default stack, as a host might
+  let mut engine = Engine::new();
+  let error = engine.eval( "fun f(n) { return f(n + 1); } f(0);" ).err().unwrap();
+  assert_eq!( error.errors[ 0 ].msg, "Stack overflow." );
+  eval( &mut engine, "fun g(n) { if (n == 0) return 0; return g(n - 1); }" );
+  assert_eq!( eval( &mut engine, &format!( "g({});", rlox::ENGINE_MAX_DEPTH - 1 ) ), "0" );
+}
+
+#[test]
+fn reset_forgets_the_exit_code() {
+  let mut engine = Engine::new();
+  eval( &mut engine, "exit(2);" );
+  assert_eq!( engine.exit_code(), Some( 2 ) );
+  engine.reset();
+  assert_eq!( engine.exit_code(), None );
+}
@@ -0,0 +1,72 @@
+use std::{env, fs, path::PathBuf, process::{Command, Output}};
+
+// a messy script touching most statements, with comments to keep
+const MESSY: &str = include_str!( "scripts/shared/fmt/messy.lox" );
+
+// the script the formatter was written for
+const MAIN: &str = include_str!( "../main.lox" );
+
+fn script( name: &str, src: &str ) -> PathBuf {
+  let path = env::temp_dir().join( format!( "rlox-fmt-{}-{}.lox", name, std::process::id() ) );
+  fs::write( &path, src ).unwrap();
+  path
+}
+
+fn rlox( args: &[&str], path: &PathBuf ) -> Output {
+  Command::new( env!( "CARGO_BIN_EXE_rlox" ) ).args( args ).arg( path ).output().unwrap()
+}
+
+// formats a copy of `src`, returning the formatted text
+fn format( name: &str, src: &str ) -> String {
+  let path = script( name, src );
+  let output = rlox( &[ "fmt" ], &path );
+  assert_eq!( output.status.code(), Some( 0 ), "{}", String::from_utf8_lossy( &output.stderr ) );
+  let formatted = fs::read_to_string( &path ).unwrap();
+  let _ = fs::remove_file( &path );
+  formatted
+}
+
+// what running `src` prints, and its exit code
+fn run( name: &str, src: &str ) -> ( String, Option<i32> ) {
+  let path = script( name, src );
+  let output = rlox( &[ "run" ], &path );
+  let _ = fs::remove_file( &path );
+  ( String::from_utf8_lossy( &output.stdout ).into_owned(), output.status.code() )
+}
+
+#[test]
+fn formatting_is_idempotent() {
+  for ( name, src ) in [ ( "messy", MESSY ), ( "main", MAIN ) ] {
+    let once = format( &format!( "{}-once", name ), src );
+    let twice = format( &format!( "{}-twice", name ), &once );
+    assert_eq!( once, twice );
+
+    let path = script( &format!( "{}-check", name ), &once );
+    let output = rlox( &[ "fmt", "--check" ], &path );
+    let _ = fs::remove_file( &path );
+    assert_eq!( output.status.code(), Some( 0 ) );
+    assert!( output.stdout.is_empty() );
+  }
+}
+
+#[test]
+fn formatting_keeps_behaviour_and_comments() {
+  for ( name, src ) in [ ( "messy", MESSY ), ( "main", MAIN ) ] {
+    let formatted = format( &format!( "{}-source", name ), src );
+    assert_eq!( run( &format!( "{}-before", name ), src ), run( &format!( "{}-after", name ), &formatted ) );
+    for comment in src.lines().filter_map( | line | line.find( "//" ).map( | idx | line[ idx.. ].trim_end() ) ) {
+      assert!( formatted.contains( comment ), "lost '{}' in:\n{}", comment, formatted );
+    }
+  }
+}
+
+#[test]
+fn check_lists_unformatted_scripts_and_leaves_them() {
+  let path = script( "unformatted", MESSY );
+  let output = rlox( &[ "fmt", "--check" ], &path );
+  let after = fs::read_to_string( &path ).unwrap();
+  let _ = fs::remove_file( &path );
+  assert_eq!( output.status.code(), Some( 1 ) );
+  assert_eq!( String::from_utf8_lossy( &output.stdout ).trim(), path.display().to_string() );
+  assert_eq!( after, MESSY );
+}
@@ -0,0 +1,85 @@
+use std::{env, fs, path::{Path, PathBuf}, process::Command};
+
+use rlox::{syntax_tree, test_dir, with_stack, Backend, Status};
+
+// scripts every backend runs, and those only the tree-walk interpreter does [ for-in ]
+const SHARED: &str = concat!( env!( "CARGO_MANIFEST_DIR" ), "/tests/scripts/shared" );
+const TREE_WALK: &str = concat!( env!( "CARGO_MANIFEST_DIR" ), "/tests/scripts/tree_walk" );
+
+// runs the scripts under `dir` as `rlox test` does, on a stack deep enough for their limits
+fn passes( dir: &Path, backend: Backend ) -> bool {
+  let dir = dir.display().to_string();
+  with_stack( move || test_dir( &dir, backend ) == Status::Success )
+}
+
+fn scripts( dir: &Path, paths: &mut Vec<PathBuf> ) {
+  for entry in fs::read_dir( dir ).unwrap() {
+    let path = entry.unwrap().path();
+    if path.is_dir() {
+      scripts( &path, paths );
+    } else if path.extension().is_some_and( | ext | ext == "lox" ) {
+      paths.push( path );
+    }
+  }
+}
+
+// copies the scripts under `from` into `to`, keeping their layout
+fn copy( from: &Path, to: &Path ) {
+  fs::create_dir_all( to ).unwrap();
+  for entry in fs::read_dir( from ).unwrap() {
+    let path = entry.unwrap().path();
+    let target = to.join( path.file_name().unwrap() );
+    if path.is_dir() {
+      copy( &path, &target );
+    } else {
+      fs::copy( &path, &target ).unwrap();
+    }
+  }
+}
+
+#[test]
+fn shared_scripts_pass_on_the_tree_walk_interpreter() {
+  assert!( passes( Path::new( SHARED ), Backend::TreeWalk ) );
+}
+
+#[test]
+fn shared_scripts_pass_on_the_vm() {
+  assert!( passes( Path::new( SHARED ), Backend::ByteCode ) );
+}
+
+#[test]
+fn tree_walk_scripts_pass() {
+  assert!( passes( Path::new( TREE_WALK ), Backend::TreeWalk ) );
+}
+
+#[test]
+fn syntax_trees_print_every_script_back() {
+  let mut paths = Vec::new();
+  scripts( Path::new( SHARED ), &mut paths );
+  scripts( Path::new( TREE_WALK ), &mut paths );
+  for path in paths {
+    let src = fs::read_to_string( &path ).unwrap();
+    let ( tree, errors ) = syntax_tree( &src );
+    assert!( errors.is_empty(), "{}", path.display() );
+    assert_eq!( tree.to_string(), src, "{}", path.display() );
+  }
+}
+
+#[test]
+fn formatted_scripts_still_pass() {
+  let dir = env::temp_dir().join( format!( "rlox-formatted-{}", std::process::id() ) );
+  copy( Path::new( SHARED ), &dir.join( "shared" ) );
+  copy( Path::new( TREE_WALK ), &dir.join( "tree_walk" ) );
+  let mut paths = Vec::new();
+  scripts( &dir, &mut paths );
+  let rlox = | args: &[&str] | Command::new( env!( "CARGO_BIN_EXE_rlox" ) ).args( args ).args( &paths ).output().unwrap();
+  let formatted = rlox( &[ "fmt" ] );
+  let checked = rlox( &[ "fmt", "--check" ] );
+  let passed = passes( &dir.join( "shared" ), Backend::TreeWalk )
+    && passes( &dir.join( "shared" ), Backend::ByteCode )
+    && passes( &dir.join( "tree_walk" ), Backend::TreeWalk );
+  let _ = fs::remove_dir_all( &dir );
+  assert_eq!( formatted.status.code(), Some( 0 ) );
+  assert_eq!( checked.status.code(), Some( 0 ), "{}", String::from_utf8_lossy( &checked.stdout ) );
+  assert!( passed );
+}
@@ -0,0 +1,56 @@
+use std::{env, fs, process::{Command, Output}};
+
+use rlox::{DEFAULT_MAX_DEPTH, MAX_DEPTH};
+
+// nests as many statements and expressions as a call reasonably might
+const RECURSE: &str = "
+fun r(n) {
+  if (n >= 0) {
+    while (true) {
+      var x = [1, {\"a\": (((n + 1) * 2) - 1)}];
+      try {
+        {
+          return r(((((n + 1)))));
+        }
+      } finally {
+        x = nil;
+      }
+    }
+  }
+}
+r(0);
+";
+
+fn run( name: &str, args: &[&str] ) -> Output {
+  let path = env::temp_dir().join( format!( "rlox-{}-{}.lox", name, std::process::id() ) );
+  fs::write( &path, RECURSE ).unwrap();
+  let output = Command::new( env!( "CARGO_BIN_EXE_rlox" ) )
+    .arg( "run" ).args( args ).arg( &path )
+    .output().unwrap();
+  let _ = fs::remove_file( &path );
+  output
+}
+
+fn assert_overflows( output: Output ) {
+  let stderr = String::from_utf8_lossy( &output.stderr );
+  assert_eq!( output.status.code(), Some( 70 ), "{}", stderr );
+  assert!( stderr.contains( "Stack overflow." ), "{}", stderr );
+}
+
+#[test]
+fn default_depth_reports_stack_overflow() {
+  assert_overflows( run( "default", &[] ) );
+  assert_overflows( run( "default-explicit", &[ "--max-depth", &DEFAULT_MAX_DEPTH.to_string() ] ) );
+}
+
+#[test]
+fn max_depth_reports_stack_overflow() {
+  assert_overflows( run( "max", &[ "--max-depth", &MAX_DEPTH.to_string() ] ) );
+  assert_overflows( run( "max-vm", &[ "--vm", "--max-depth", &MAX_DEPTH.to_string() ] ) );
+}
+
+#[test]
+fn depth_beyond_the_stack_is_refused() {
+  let output = run( "beyond", &[ "--max-depth", &( MAX_DEPTH + 1 ).to_string() ] );
+  assert_eq!( output.status.code(), Some( 64 ) );
+}
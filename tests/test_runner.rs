@@ -0,0 +1,43 @@
+use std::{env, fs, path::PathBuf, process::Command};
+
+// a directory holding the given scripts, for `rlox test` to run
+fn suite( name: &str, scripts: &[( &str, &str )] ) -> PathBuf {
+  let dir = env::temp_dir().join( format!( "rlox-suite-{}-{}", name, std::process::id() ) );
+  fs::create_dir_all( &dir ).unwrap();
+  for ( file, src ) in scripts {
+    fs::write( dir.join( file ), src ).unwrap();
+  }
+  dir
+}
+
+// what `rlox test` prints for `dir`, and its exit code
+fn test( dir: &PathBuf ) -> ( String, Option<i32> ) {
+  let output = Command::new( env!( "CARGO_BIN_EXE_rlox" ) ).arg( "test" ).arg( dir ).output().unwrap();
+  let _ = fs::remove_dir_all( dir );
+  ( String::from_utf8_lossy( &output.stdout ).into_owned(), output.status.code() )
+}
+
+#[test]
+fn passing_scripts_are_listed() {
+  let dir = suite( "pass", &[ ( "a.lox", "print 1; // expect: 1\n" ) ] );
+  let path = dir.join( "a.lox" ).display().to_string();
+  let ( stdout, code ) = test( &dir );
+  assert_eq!( code, Some( 0 ) );
+  assert_eq!( stdout, format!( "PASS {}\nAll 1 tests passed.\n", path ) );
+}
+
+#[test]
+fn wrong_output_is_shown_as_a_diff() {
+  let dir = suite( "diff", &[ ( "b.lox", "print 1;\nprint 3;\n// expect: 1\n// expect: 2\n// expect: 4\n" ) ] );
+  let path = dir.join( "b.lox" ).display().to_string();
+  let ( stdout, code ) = test( &dir );
+  assert_ne!( code, Some( 0 ) );
+  assert_eq!( stdout, format!( "FAIL {}
+     Output differs [ - expected, + actual ]:
+       1
+     - 2
+     + 3
+     - 4
+0 tests passed. 1 tests failed.
+", path ) );
+}